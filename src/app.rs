@@ -1,5 +1,14 @@
+use crate::control::{ControlRequest, ControlResponse, TimerStatus};
+use crate::hooks::HookFailure;
+use crate::keymap::{Action, KeyContext, Keymap};
 use crate::romodoro::Pomodoro;
 use crate::settings::*;
+use crate::export::{self, ExportFilter, ExportFormat};
+use crate::stats::{InterruptionKind, StatsTab};
+use crate::tasks::TaskList;
+use crate::timer::TimerSnapshot;
+use crate::EXTEND_TIME;
+use chrono::Local;
 use core::panic;
 use crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind};
 use ratatui::DefaultTerminal;
@@ -8,6 +17,13 @@ use std::io;
 use std::rc::Rc;
 use tokio_util::sync::CancellationToken;
 
+/// Note typed for the interruption that was just logged.
+#[derive(Debug, Clone)]
+pub struct NoteInput {
+    pub kind: InterruptionKind,
+    pub text: String,
+}
+
 #[derive(Debug)]
 pub struct App {
     exit: bool,
@@ -15,20 +31,39 @@ pub struct App {
     selected_tab: usize,
     settings: Rc<RefCell<SettingsTab>>,
     settings_popup_showing: bool,
+    /// Preset picked on the timer tab, waiting for the reset to be confirmed.
+    pending_preset: Option<String>,
+    stats_tab: StatsTab,
+    subject_input: Option<SubjectInput>,
+    task_input: Option<SubjectInput>,
+    note_input: Option<NoteInput>,
+    resume_offer: Option<TimerSnapshot>,
+    keymap: Keymap,
 }
 pub enum Event {
     TimerTick(i64),
     KeyPress(KeyEvent),
     TerminalEvent,
+    /// Request from the control socket, answered on the sender.
+    Control(ControlRequest, tokio::sync::oneshot::Sender<ControlResponse>),
+    HookFailed(HookFailure),
 }
 impl App {
     pub fn new(pomodoro: Pomodoro, settings: Rc<RefCell<SettingsTab>>) -> Self {
+        let keymap = Keymap::try_from(&settings.borrow().keymap).unwrap_or_default();
         App {
+            keymap,
             pomodoro,
             exit: false,
             selected_tab: 0,
             settings,
             settings_popup_showing: false,
+            pending_preset: None,
+            stats_tab: StatsTab::default(),
+            subject_input: None,
+            task_input: None,
+            note_input: None,
+            resume_offer: None,
         }
     }
     pub async fn run(
@@ -58,6 +93,9 @@ impl App {
         let timer_task = tokio::spawn(async move {
             Pomodoro::handle_timer(&mut time_rx, tx_timer, timer_comm_cancel).await;
         });
+        #[cfg(unix)]
+        let control_task = crate::control::spawn(tx.clone(), cancelation_token.clone()).await;
+        self.pomodoro.set_hook_sender(tx.clone());
         self.pomodoro.create_countdown(timer_cancel).await;
 
         terminal.draw(|frame| self.draw(frame))?;
@@ -71,54 +109,256 @@ impl App {
                         self.pomodoro.handle_timer_responses(time).await;
                     }
                     Event::TerminalEvent => {}
+                    Event::Control(request, reply) => {
+                        let _ = reply.send(self.handle_control(request).await);
+                    }
+                    Event::HookFailed(failure) => self.pomodoro.set_hook_failure(Some(failure)),
                 }
             }
             terminal.draw(|frame| self.draw(frame))?;
         }
         cancelation_token.cancel();
         timer_task.await?;
+        #[cfg(unix)]
+        if let Some(control_task) = control_task {
+            control_task.await?;
+        }
         input_task.abort();
         Ok(())
     }
+    /// Runs a control socket command like the matching timer key would.
+    async fn handle_control(&mut self, request: ControlRequest) -> ControlResponse {
+        let running = self.pomodoro.timer.get_running();
+        match request {
+            ControlRequest::Status => {}
+            ControlRequest::Start if running => {}
+            ControlRequest::Stop if !running => {}
+            ControlRequest::Start | ControlRequest::Stop | ControlRequest::Toggle => self.pomodoro.cycle().await,
+            ControlRequest::Skip => {
+                self.pomodoro.skip().await;
+            }
+            ControlRequest::Extend { seconds } => self.pomodoro.extend(seconds).await,
+            ControlRequest::Rewind => self.pomodoro.rewind().await,
+            ControlRequest::Restart => self.pomodoro.restart_cycle().await,
+        }
+        ControlResponse::status(TimerStatus::of(&self.pomodoro))
+    }
     async fn handle_key_event(&mut self, key_event: KeyEvent) {
+        if let Some(snapshot) = self.resume_offer.take() {
+            let action = self
+                .keymap
+                .action(KeyContext::Confirm, &key_event)
+                .or(self.keymap.action(KeyContext::Global, &key_event));
+            match action {
+                Some(Action::Yes) => self.pomodoro.restore(&snapshot).await,
+                Some(Action::No) => self.pomodoro.save_snapshot(),
+                Some(Action::Quit) => self.exit(),
+                _ => self.resume_offer = Some(snapshot),
+            }
+            return;
+        }
+        // the subject popup takes all keys while it's open
+        if self.subject_input.is_some() {
+            self.handle_subject_input(key_event);
+            return;
+        }
+        if self.task_input.is_some() {
+            self.handle_task_input(key_event);
+            return;
+        }
+        if self.note_input.is_some() {
+            self.handle_note_input(key_event);
+            return;
+        }
         //global
-        match key_event.code {
-            KeyCode::Char('Q') => self.exit(),
-            KeyCode::Tab if !self.settings_popup_showing => self.change_tab(),
+        match self.keymap.action(KeyContext::Global, &key_event) {
+            Some(Action::Quit) => self.exit(),
+            Some(Action::NextTab) if !self.get_show_popup() => self.change_tab(),
             _ => {}
         }
-        match self.selected_tab {
-            0 => {
-                // timer
-                match key_event.code {
-                    KeyCode::Char(' ') => self.pomodoro.cycle().await,
-                    _ => {}
-                }
+        let context = match self.selected_tab {
+            _ if self.get_show_popup() => KeyContext::Confirm,
+            0 => KeyContext::Timer,
+            1 => KeyContext::Settings,
+            2 => KeyContext::Stats,
+            _ => KeyContext::Tasks,
+        };
+        let Some(action) = self.keymap.action(context, &key_event) else {return};
+        match action {
+            // timer
+            Action::ToggleTimer => self.pomodoro.cycle().await,
+            Action::NextSubject => self.pomodoro.timer.next_subject(),
+            Action::Skip => {
+                self.pomodoro.skip().await;
             }
+            Action::Extend => self.pomodoro.extend(EXTEND_TIME).await,
+            Action::Rewind => self.pomodoro.rewind().await,
+            Action::RestartCycle => self.pomodoro.restart_cycle().await,
+            Action::NextTask => self.edit_tasks(|tasks| tasks.next_active()),
+            Action::NextPreset => self.next_preset().await,
+            Action::InternalInterruption => self.interrupt(InterruptionKind::Internal),
+            Action::ExternalInterruption => self.interrupt(InterruptionKind::External),
             // settings
-            1 => match self.settings_popup_showing {
-                false => match key_event.code {
-                    KeyCode::Down => self.settings.borrow_mut().select_down(),
-                    KeyCode::Up => self.settings.borrow_mut().select_up(),
-                    KeyCode::Right => self.settings.borrow_mut().increment(),
-                    KeyCode::Left => self.settings.borrow_mut().decrement(),
-                    KeyCode::Char(' ') => self.update_settings().await,
-                    KeyCode::Char('r') => self.settings.borrow_mut().restore_defaults(),
-                    _ => {}
-                },
-                true => match key_event.code {
-                    KeyCode::Char('y') if self.settings_popup_showing => {
-                        self.overwrite_timer().await
+            Action::Down => self.settings.borrow_mut().select_down(),
+            Action::Up => self.settings.borrow_mut().select_up(),
+            Action::Increase => self.settings.borrow_mut().increment(),
+            Action::Decrease => self.settings.borrow_mut().decrement(),
+            Action::Confirm => self.update_settings().await,
+            Action::RestoreDefaults => self.settings.borrow_mut().restore_defaults(),
+            Action::AddSubject => self.subject_input = Some(SubjectInput::default()),
+            Action::RenameSubject => self.start_subject_rename(),
+            Action::DeleteSubject => self.delete_subject(),
+            Action::Yes => self.overwrite_timer().await,
+            Action::No => {
+                self.settings_popup_showing = false;
+                self.pending_preset = None;
+            }
+            // stats
+            Action::ScrollBack => self.stats_tab.scroll_back(),
+            Action::ScrollForward => self.stats_tab.scroll_forward(),
+            Action::ToggleRange => self.stats_tab.toggle_range(),
+            Action::ExportCsv => self.export_stats(ExportFormat::Csv),
+            Action::ExportJson => self.export_stats(ExportFormat::Json),
+            // tasks
+            Action::TaskUp => self.pomodoro.get_tasks_ref().borrow_mut().select_up(),
+            Action::TaskDown => self.pomodoro.get_tasks_ref().borrow_mut().select_down(),
+            Action::MoveTaskUp => self.edit_tasks(|tasks| tasks.move_selected(true)),
+            Action::MoveTaskDown => self.edit_tasks(|tasks| tasks.move_selected(false)),
+            Action::AddTask => self.task_input = Some(SubjectInput::default()),
+            Action::RenameTask => self.start_task_rename(),
+            Action::DeleteTask => self.edit_selected_task(|tasks, i| {
+                tasks.delete(i);
+            }),
+            Action::CompleteTask => self.edit_selected_task(TaskList::toggle_done),
+            Action::ActivateTask => self.edit_selected_task(TaskList::toggle_active),
+            Action::IncreaseEstimate => self.edit_selected_task(|tasks, i| tasks.change_estimate(i, true)),
+            Action::DecreaseEstimate => self.edit_selected_task(|tasks, i| tasks.change_estimate(i, false)),
+            Action::Quit | Action::NextTab => {}
+        }
+    }
+
+    fn handle_subject_input(&mut self, key_event: KeyEvent) {
+        let Some(input) = self.subject_input.as_mut() else {return};
+        match key_event.code {
+            KeyCode::Char(c) => input.text.push(c),
+            KeyCode::Backspace => {
+                input.text.pop();
+            }
+            KeyCode::Esc => self.subject_input = None,
+            KeyCode::Enter => {
+                let input = self.subject_input.take().expect("Checked above");
+                let mut settings = self.settings.borrow_mut();
+                match input.editing {
+                    Some(index) => {
+                        let old = settings.stats_setting.subjects[index].clone();
+                        if settings.rename_subject(index, &input.text).is_some()
+                            && self.pomodoro.timer.get_subject() == Some(old)
+                        {
+                            self.pomodoro
+                                .timer
+                                .set_subject(Some(settings.stats_setting.subjects[index].clone()));
+                        }
                     }
-                    KeyCode::Char('n') if self.settings_popup_showing => {
-                        self.settings_popup_showing = false
+                    None => {
+                        settings.add_subject(&input.text);
                     }
-                    _ => {}
-                },
-            },
+                }
+            }
             _ => {}
         }
     }
+    fn start_subject_rename(&mut self) {
+        let settings = self.settings.borrow();
+        if let Some(index) = settings.get_selected_subject() {
+            self.subject_input = Some(SubjectInput {
+                text: settings.stats_setting.subjects[index].clone(),
+                editing: Some(index),
+            });
+        }
+    }
+    fn delete_subject(&mut self) {
+        let index = self.settings.borrow().get_selected_subject();
+        let removed = index.and_then(|i| self.settings.borrow_mut().delete_subject(i));
+        if removed.is_some() && self.pomodoro.timer.get_subject() == removed {
+            self.pomodoro.timer.set_subject(None);
+        }
+    }
+
+    /// Exports the days shown in the stats tab.
+    fn export_stats(&mut self, format: ExportFormat) {
+        let (from, to) = self.stats_tab.get_window(Local::now().date_naive());
+        let filter = ExportFilter { from: Some(from), to: Some(to), subject: None };
+        let stats = self.pomodoro.get_stats_ref();
+        let stats = stats.borrow();
+        let path = export::export_dir(&stats).join(filter.file_name(format));
+        self.stats_tab.message = Some(match export::export_to_file(&stats, &filter, format, &path) {
+            Ok(count) => format!("Exported {} sessions to {}", count, path.display()),
+            Err(e) => format!("Export failed: {e}"),
+        });
+    }
+    /// Logs the interruption right away, the note can be typed afterwards or skipped.
+    fn interrupt(&mut self, kind: InterruptionKind) {
+        if self.pomodoro.interrupt(kind) {
+            self.note_input = Some(NoteInput { kind, text: String::new() });
+        }
+    }
+    fn handle_note_input(&mut self, key_event: KeyEvent) {
+        let Some(input) = self.note_input.as_mut() else {return};
+        match key_event.code {
+            KeyCode::Char(c) => input.text.push(c),
+            KeyCode::Backspace => {
+                input.text.pop();
+            }
+            KeyCode::Esc => self.note_input = None,
+            KeyCode::Enter => {
+                let input = self.note_input.take().expect("Checked above");
+                self.pomodoro.note_interruption(&input.text);
+            }
+            _ => {}
+        }
+    }
+    fn handle_task_input(&mut self, key_event: KeyEvent) {
+        let Some(input) = self.task_input.as_mut() else {return};
+        match key_event.code {
+            KeyCode::Char(c) => input.text.push(c),
+            KeyCode::Backspace => {
+                input.text.pop();
+            }
+            KeyCode::Esc => self.task_input = None,
+            KeyCode::Enter => {
+                let input = self.task_input.take().expect("Checked above");
+                self.edit_tasks(|tasks| match input.editing {
+                    Some(index) => {
+                        tasks.rename(index, &input.text);
+                    }
+                    None => {
+                        tasks.add(&input.text);
+                    }
+                });
+            }
+            _ => {}
+        }
+    }
+    fn start_task_rename(&mut self) {
+        let tasks = self.pomodoro.get_tasks_ref();
+        let tasks = tasks.borrow();
+        if let Some(index) = tasks.get_selected() {
+            self.task_input = Some(SubjectInput { text: tasks.get_tasks()[index].name.clone(), editing: Some(index) });
+        }
+    }
+    /// Changes the task list and saves it right away.
+    fn edit_tasks(&self, edit: impl FnOnce(&mut TaskList)) {
+        let tasks = self.pomodoro.get_tasks_ref();
+        edit(&mut tasks.borrow_mut());
+        let _ = tasks.borrow().save_to_file();
+    }
+    fn edit_selected_task(&self, edit: impl FnOnce(&mut TaskList, usize)) {
+        self.edit_tasks(|tasks| {
+            if let Some(index) = tasks.get_selected() {
+                edit(tasks, index);
+            }
+        });
+    }
 
     async fn handle_inputs(
         tx: tokio::sync::mpsc::Sender<Event>,
@@ -167,8 +407,21 @@ impl App {
             .get_pomodoro_setting(PomodoroSettings::Iterations(None));
         let current_break_time: PomodoroSettings = self.pomodoro.timer.get_break_state().into();
         let current_work_time: PomodoroSettings = self.pomodoro.timer.get_work_state().into();
+        let long_break_time = self
+            .settings
+            .borrow()
+            .get_pomodoro_setting(PomodoroSettings::LongBreakTime(None));
+        let long_break_interval = self
+            .settings
+            .borrow()
+            .get_pomodoro_setting(PomodoroSettings::LongBreakInterval(None));
         let current_iterations: PomodoroSettings =
-            PomodoroSettings::Iterations(Some(self.pomodoro.timer.get_total_iterations()));
+            PomodoroSettings::Iterations(Some(self.pomodoro.timer.get_iterations_setting()));
+        let current_long_break_time: PomodoroSettings =
+            self.pomodoro.timer.get_long_break_state().into();
+        let current_long_break_interval: PomodoroSettings = PomodoroSettings::LongBreakInterval(
+            Some(self.pomodoro.timer.get_long_break_interval()),
+        );
         if current_break_time != break_time {
             self.pomodoro.set_setting(break_time).await;
         }
@@ -178,12 +431,36 @@ impl App {
         if current_iterations != iterations {
             self.pomodoro.set_setting(iterations).await;
         }
+        if current_long_break_time != long_break_time {
+            self.pomodoro.set_setting(long_break_time).await;
+        }
+        if current_long_break_interval != long_break_interval {
+            self.pomodoro.set_setting(long_break_interval).await;
+        }
+        let phases = self.settings.borrow().get_phases();
+        if self.pomodoro.timer.get_phases() != phases.as_slice() {
+            self.pomodoro.set_phases(phases).await;
+        }
     }
     pub async fn overwrite_timer(&mut self) {
         self.pomodoro.timer.stop().await;
         self.settings_popup_showing = false;
+        if let Some(name) = self.pending_preset.take() {
+            let _ = self.settings.borrow_mut().apply_preset(&name);
+        }
         self.update_settings().await;
     }
+    /// Switches the timer to the next preset, asking first if that resets a running one.
+    async fn next_preset(&mut self) {
+        let Some(name) = self.settings.borrow().preset_after(true) else {return};
+        if self.pomodoro.timer.get_running() {
+            self.pending_preset = Some(name);
+            return;
+        }
+        if self.settings.borrow_mut().apply_preset(&name).is_ok() {
+            self.update_settings().await;
+        }
+    }
 
     pub fn get_selected_tab(&self) -> usize {
         self.selected_tab
@@ -194,12 +471,38 @@ impl App {
     pub fn get_pomodoro_ref(&self) -> &Pomodoro {
         &self.pomodoro
     }
+    pub fn get_stats_tab(&self) -> &StatsTab {
+        &self.stats_tab
+    }
+    /// Asks on the next draw whether to continue the interrupted session.
+    pub fn offer_resume(&mut self, snapshot: TimerSnapshot) {
+        self.resume_offer = Some(snapshot);
+    }
+    pub fn get_resume_offer(&self) -> Option<&TimerSnapshot> {
+        self.resume_offer.as_ref()
+    }
+    pub fn get_subject_input(&self) -> Option<&SubjectInput> {
+        self.subject_input.as_ref()
+    }
+    pub fn get_task_input(&self) -> Option<&SubjectInput> {
+        self.task_input.as_ref()
+    }
+    pub fn get_note_input(&self) -> Option<&NoteInput> {
+        self.note_input.as_ref()
+    }
+    pub fn get_keymap(&self) -> &Keymap {
+        &self.keymap
+    }
+    /// Whether the reset confirmation is open, for the settings or a preset.
     pub fn get_show_popup(&self) -> bool {
-        self.settings_popup_showing
+        self.settings_popup_showing || self.pending_preset.is_some()
+    }
+    pub fn get_pending_preset(&self) -> Option<&str> {
+        self.pending_preset.as_deref()
     }
 
     fn change_tab(&mut self) {
-        if self.selected_tab == 2 {
+        if self.selected_tab == 3 {
             self.selected_tab = 0;
         } else {
             self.selected_tab += 1;
@@ -213,8 +516,6 @@ impl App {
 
 #[cfg(test)]
 mod test {
-    use super::*;
-
     //  #[test]
     //   #[ignore]
     //   fn multithread_works() {
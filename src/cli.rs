@@ -0,0 +1,288 @@
+use std::{fmt::Write, path::PathBuf};
+
+use chrono::{Days, NaiveDate};
+use clap::{Parser, Subcommand};
+
+use crate::{control::DEFAULT_STATUS_FORMAT, export::ExportFormat, import::{DurationUnit, ImportFormat}, settings::{SettingsTab, TimerSettings}, stats::SessionStore};
+
+/// A pomodoro timer for the terminal.
+#[derive(Debug, Parser)]
+#[command(name = "romodoro", version, about)]
+pub struct Cli {
+    /// Work time for this run, e.g. 25m, 1h30m or 90s (not saved)
+    #[arg(long, global = true, value_parser = parse_duration)]
+    pub work: Option<i64>,
+
+    /// Break time for this run (not saved)
+    #[arg(long = "break", global = true, value_parser = parse_duration)]
+    pub break_time: Option<i64>,
+
+    /// Long break time for this run (not saved)
+    #[arg(long, global = true, value_parser = parse_duration)]
+    pub long_break: Option<i64>,
+
+    /// Number of work iterations for this run (not saved)
+    #[arg(long, global = true, value_parser = clap::value_parser!(u8).range(1..))]
+    pub iterations: Option<u8>,
+
+    /// Preset from [presets.<name>] in the config for this run, the other flags go on top of it (not saved)
+    #[arg(long, global = true)]
+    pub preset: Option<String>,
+
+    /// Use this config file instead of the default one, history, tasks and the timer state are kept next to it
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Don't write the config back on exit
+    #[arg(long, global = true)]
+    pub no_save: bool,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Open the timer (the default)
+    Start {
+        /// Run without the TUI and print progress to stdout
+        #[arg(long)]
+        headless: bool,
+        /// Print JSON lines instead of text, implies --headless
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a summary of recorded sessions
+    Stats {
+        /// How many days to list
+        #[arg(long, default_value_t = 7)]
+        days: u64,
+    },
+    /// Write recorded sessions as CSV or JSON
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+        /// First day to include, e.g. 2025-03-01
+        #[arg(long)]
+        from: Option<NaiveDate>,
+        /// Last day to include
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        /// Only sessions with this subject
+        #[arg(long)]
+        subject: Option<String>,
+        /// Write to this file instead of stdout
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Add sessions from another tool's CSV or JSON export
+    Import {
+        file: PathBuf,
+        /// Guessed from the file extension if left out
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+        /// Column mapping from [import_mappings.<name>] in the config
+        #[arg(long)]
+        mapping: Option<String>,
+        /// Where a field is in the file, e.g. --map "start=Start Time". Fields: start, end, duration,
+        /// kind, subject, task, completed
+        #[arg(long = "map", value_name = "FIELD=COLUMN")]
+        columns: Vec<String>,
+        /// Unit of the duration column
+        #[arg(long, value_enum)]
+        duration_unit: Option<DurationUnit>,
+        /// Only report what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the state of the running timer, for prompts and status bars
+    Status {
+        /// Placeholders: {state}, {phase}, {mm}, {ss}, {iteration}, {total}, {subject}, {task},
+        /// {interruptions}, {paused}
+        #[arg(long, default_value = DEFAULT_STATUS_FORMAT)]
+        format: String,
+        /// Print the full state as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print the configuration in use, including command line overrides
+    Show,
+    /// Print where the configuration file is
+    Path,
+    /// Store the Pixela token, read from stdin if left out. PIXELA_TOKEN takes precedence over it
+    PixelaToken {
+        token: Option<String>,
+    },
+}
+
+impl Cli {
+    pub fn has_overrides(&self) -> bool {
+        self.work.is_some()
+            || self.break_time.is_some()
+            || self.long_break.is_some()
+            || self.iterations.is_some()
+            || self.preset.is_some()
+    }
+    pub fn apply_overrides(&self, timer_settings: &mut TimerSettings) {
+        if let Some(work) = self.work {timer_settings.work_time = work}
+        if let Some(break_time) = self.break_time {timer_settings.break_time = break_time}
+        if let Some(long_break) = self.long_break {timer_settings.long_break_time = long_break}
+        if let Some(iterations) = self.iterations {timer_settings.iterations = iterations}
+    }
+    /// Undoes `--preset` and the overrides before the settings are saved. `saved` is what the config had and
+    /// `applied` what the run started with, fields changed since then are kept.
+    pub fn restore_overrides(&self, settings: &mut SettingsTab, saved: &TimerSettings, saved_preset: Option<String>, applied: &TimerSettings) {
+        fn restore<T: PartialEq + Clone>(current: &mut T, saved: &T, applied: &T) {
+            if current == applied {*current = saved.clone()}
+        }
+        let current = &mut settings.timer_settings;
+        restore(&mut current.work_time, &saved.work_time, &applied.work_time);
+        restore(&mut current.break_time, &saved.break_time, &applied.break_time);
+        restore(&mut current.iterations, &saved.iterations, &applied.iterations);
+        restore(&mut current.long_break_time, &saved.long_break_time, &applied.long_break_time);
+        restore(&mut current.long_break_interval, &saved.long_break_interval, &applied.long_break_interval);
+        restore(&mut current.profile, &saved.profile, &applied.profile);
+        if self.preset.is_some() && settings.preset == self.preset {
+            settings.preset = saved_preset;
+        }
+    }
+}
+
+/// Parses durations like `25m`, `90s`, `1h30m`. A bare number is taken as minutes.
+pub fn parse_duration(input: &str) -> std::result::Result<i64, String> {
+    let input = input.trim();
+    let too_long = || format!("\"{input}\" is too long");
+    if let Ok(minutes) = input.parse::<i64>() {
+        return match minutes {
+            1.. => minutes.checked_mul(60).ok_or_else(too_long),
+            _ => Err("duration has to be positive".to_string()),
+        };
+    }
+    let mut total: i64 = 0;
+    let mut number = String::new();
+    for ch in input.chars() {
+        match ch {
+            '0'..='9' => number.push(ch),
+            'h' | 'm' | 's' => {
+                let value: i64 = number.parse().map_err(|_| match number.is_empty() {
+                    true => format!("missing number before '{ch}' in \"{input}\""),
+                    false => too_long(),
+                })?;
+                let unit = match ch {
+                    'h' => 3600,
+                    'm' => 60,
+                    _ => 1,
+                };
+                total = value.checked_mul(unit).and_then(|seconds| total.checked_add(seconds)).ok_or_else(too_long)?;
+                number.clear();
+            }
+            _ => return Err(format!("unexpected '{ch}' in \"{input}\", use e.g. 25m, 1h30m or 90s")),
+        }
+    }
+    if !number.is_empty() {
+        return Err(format!("missing unit after {number} in \"{input}\""));
+    }
+    match total {
+        1.. => Ok(total),
+        _ => Err("duration has to be positive".to_string()),
+    }
+}
+
+/// Plain text summary printed by `romodoro stats`.
+pub fn stats_report(store: &SessionStore, today: NaiveDate, days: u64) -> String {
+    let from = today.checked_sub_days(Days::new(days.saturating_sub(1))).unwrap_or(today);
+    let (current_streak, longest_streak) = store.streaks(today);
+    let mut report = String::new();
+    let _ = writeln!(report, "Today: {} min", store.focus_on(today) / 60);
+    let _ = writeln!(report, "Current streak: {} days", current_streak);
+    let _ = writeln!(report, "Longest streak: {} days", longest_streak);
+    let (internal, external) = store.interruptions(from, today);
+    let _ = writeln!(report, "Interruptions, last {} days: {} internal, {} external", days, internal, external);
+    let _ = writeln!(report, "\nFocused minutes, last {} days:", days);
+    for (day, seconds) in store.focus_per_day(from, today) {
+        let _ = writeln!(report, "  {}  {:>4} min", day.format("%a %Y-%m-%d"), seconds / 60);
+    }
+    let subjects = store.focus_by_subject(from, today);
+    if !subjects.is_empty() {
+        let _ = writeln!(report, "\nBy subject:");
+        for (subject, seconds) in subjects {
+            let _ = writeln!(report, "  {:<20} {:>4} min", subject, seconds / 60);
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("25m"), Ok(1500));
+        assert_eq!(parse_duration("25"), Ok(1500));
+        assert_eq!(parse_duration("1h30m"), Ok(5400));
+        assert_eq!(parse_duration("90s"), Ok(90));
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("25x").is_err());
+        assert!(parse_duration("1h30").is_err());
+        assert!(parse_duration("m").is_err());
+        assert_eq!(parse_duration("9999999999999999h"), Err("\"9999999999999999h\" is too long".to_string()));
+        assert!(parse_duration("9223372036854775807").is_err());
+        assert!(parse_duration("99999999999999999999m").is_err());
+        assert!(parse_duration("2562047788015215h2562047788015215h").is_err());
+    }
+    #[test]
+    fn overrides() {
+        let cli = Cli::parse_from(["romodoro", "start", "--work", "50m", "--break", "10m", "--iterations", "6", "--no-save"]);
+        assert!(matches!(cli.command, Some(Command::Start { headless: false, json: false })));
+        assert!(cli.no_save && cli.has_overrides());
+        let mut settings = TimerSettings::default();
+        cli.apply_overrides(&mut settings);
+        assert_eq!((settings.work_time, settings.break_time, settings.iterations), (3000, 600, 6));
+        assert!(Cli::try_parse_from(["romodoro", "--iterations", "0"]).is_err());
+    }
+    #[test]
+    fn pixela_token_command() {
+        let cli = Cli::parse_from(["romodoro", "config", "pixela-token", "secret"]);
+        assert!(matches!(cli.command, Some(Command::Config { command: ConfigCommand::PixelaToken { token: Some(ref token) } }) if token == "secret"));
+        let cli = Cli::parse_from(["romodoro", "config", "pixela-token"]);
+        assert!(matches!(cli.command, Some(Command::Config { command: ConfigCommand::PixelaToken { token: None } })));
+    }
+    #[test]
+    fn preset_then_overrides() {
+        let cli = Cli::parse_from(["romodoro", "--preset", "coding", "--break", "15m"]);
+        assert!(cli.has_overrides());
+        assert!(Cli::parse_from(["romodoro", "--preset", "coding"]).has_overrides());
+        let mut settings = crate::settings::SettingsTab::default();
+        settings.presets.insert("coding".to_string(), TimerSettings { work_time: 3000, break_time: 600, ..Default::default() });
+        settings.apply_preset(cli.preset.as_deref().unwrap()).unwrap();
+        cli.apply_overrides(&mut settings.timer_settings);
+        assert_eq!((settings.timer_settings.work_time, settings.timer_settings.break_time), (3000, 900));
+        assert!(settings.apply_preset("admin").unwrap_err().to_string().contains("unknown preset \"admin\""));
+    }
+    #[test]
+    fn overrides_are_restored_unless_changed() {
+        let cli = Cli::parse_from(["romodoro", "--preset", "coding", "--break", "15m"]);
+        let mut settings = crate::settings::SettingsTab::default();
+        settings.presets.insert("coding".to_string(), TimerSettings { work_time: 3000, break_time: 600, iterations: 6, ..Default::default() });
+        let saved = settings.timer_settings.clone();
+        settings.apply_preset("coding").unwrap();
+        cli.apply_overrides(&mut settings.timer_settings);
+        let applied = settings.timer_settings.clone();
+
+        // picked in the settings tab during the run
+        settings.timer_settings.work_time = 1200;
+        cli.restore_overrides(&mut settings, &saved, None, &applied);
+        assert_eq!(settings.timer_settings, TimerSettings { work_time: 1200, ..saved.clone() });
+        assert_eq!(settings.preset, None);
+    }
+}
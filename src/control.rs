@@ -0,0 +1,323 @@
+use std::path::PathBuf;
+
+use directories::ProjectDirs;
+use serde::*;
+
+use crate::error::{Result, SettingsError};
+use crate::romodoro::Pomodoro;
+use crate::timer::{PomodoroState, Timer};
+use crate::EXTEND_TIME;
+
+/// What `romodoro status` prints when no format is given.
+pub const DEFAULT_STATUS_FORMAT: &str = "{state} {mm}:{ss} {iteration}/{total}";
+
+/// A command sent to a running instance, one JSON object per line like `{"command": "skip"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum ControlRequest {
+    Status,
+    Start,
+    Stop,
+    Toggle,
+    Skip,
+    Extend {
+        #[serde(default = "default_extend")]
+        seconds: i64,
+    },
+    Rewind,
+    Restart,
+}
+
+/// Answer to every request, with the timer status after it was handled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlResponse {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<TimerStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimerStatus {
+    /// `work`, `break` or `long_break`.
+    pub state: String,
+    /// Label of the profile's phase, or the state for the classic timer.
+    #[serde(default)]
+    pub phase: String,
+    /// Seconds left in the interval.
+    pub time_left: i64,
+    /// Length of the interval in seconds.
+    pub duration: i64,
+    pub iteration: u8,
+    pub total_iterations: u8,
+    pub running: bool,
+    pub subject: Option<String>,
+    pub task: Option<String>,
+    pub interruptions: usize,
+}
+
+fn default_extend() -> i64 {
+    EXTEND_TIME
+}
+
+impl TimerStatus {
+    pub fn of(pomodoro: &Pomodoro) -> TimerStatus {
+        let timer = &pomodoro.timer;
+        let state = match timer.get_current_state() {
+            PomodoroState::Work(_) => "work",
+            PomodoroState::Break(_) => "break",
+            PomodoroState::LongBreak(_) => "long_break",
+        };
+        TimerStatus {
+            state: state.to_string(),
+            phase: timer.get_state_label(),
+            time_left: timer.get_timeleft().max(0),
+            duration: Timer::get_duration(&timer.get_current_state()),
+            iteration: timer.get_iteration(),
+            total_iterations: timer.get_total_iterations(),
+            running: timer.get_running(),
+            subject: timer.get_subject(),
+            task: pomodoro.get_tasks_ref().borrow().get_active().map(|task| task.name.clone()),
+            interruptions: timer.get_interruptions().len(),
+        }
+    }
+    /// Fills in `{state}`, `{phase}`, `{mm}`, `{ss}`, `{iteration}`, `{total}`, `{subject}`, `{task}`,
+    /// `{interruptions}` and `{paused}`. Anything else in braces is left as it is.
+    pub fn format(&self, template: &str) -> String {
+        let mut output = String::new();
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            let Some(close) = rest[open..].find('}').map(|close| open + close) else {break};
+            output.push_str(&rest[..open]);
+            let value = match &rest[open + 1..close] {
+                "state" => self.state_name().to_string(),
+                "phase" => self.phase.clone(),
+                "mm" => format!("{:02}", self.time_left / 60),
+                "ss" => format!("{:02}", self.time_left % 60),
+                "iteration" => self.iteration.to_string(),
+                "total" => self.total_iterations.to_string(),
+                "subject" => self.subject.clone().unwrap_or_default(),
+                "task" => self.task.clone().unwrap_or_default(),
+                "interruptions" => self.interruptions.to_string(),
+                "paused" => if self.running {String::new()} else {"paused".to_string()},
+                _ => rest[open..=close].to_string(),
+            };
+            output.push_str(&value);
+            rest = &rest[close + 1..];
+        }
+        output.push_str(rest);
+        output
+    }
+    /// State as the timer shows it, e.g. `Long Break`.
+    pub fn state_name(&self) -> &'static str {
+        match self.state.as_str() {
+            "break" => "Break",
+            "long_break" => "Long Break",
+            _ => "Work",
+        }
+    }
+}
+
+impl ControlResponse {
+    pub fn status(status: TimerStatus) -> ControlResponse {
+        ControlResponse { ok: true, status: Some(status), error: None }
+    }
+    pub fn error(error: impl ToString) -> ControlResponse {
+        ControlResponse { ok: false, status: None, error: Some(error.to_string()) }
+    }
+}
+
+/// `control.sock` in the runtime dir, or the cache dir where there is no runtime dir.
+pub fn socket_path() -> Result<PathBuf> {
+    let dirs = ProjectDirs::from("romodoro","mejxedev", "romodoro").ok_or(SettingsError::HomeDirNotFound)?;
+    Ok(dirs.runtime_dir().unwrap_or(dirs.cache_dir()).join("control.sock"))
+}
+
+/// Status of the instance running on this machine, `None` if there isn't one.
+pub fn running_status() -> Option<TimerStatus> {
+    #[cfg(unix)]
+    {
+        request(&socket_path().ok()?, &ControlRequest::Status).ok()?.status
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+#[cfg(unix)]
+pub use self::unix::*;
+
+#[cfg(unix)]
+mod unix {
+    use std::io::{BufRead, Write};
+    use std::{os::unix::fs::PermissionsExt, path::{Path, PathBuf}, time::Duration};
+
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{UnixListener, UnixStream};
+    use tokio::sync::{mpsc::Sender, oneshot};
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::app::Event;
+
+    /// Binds the socket, replacing a stale one. Fails if another instance is listening on it.
+    pub async fn bind(path: &Path) -> std::io::Result<UnixListener> {
+        if path.exists() {
+            if UnixStream::connect(path).await.is_ok() {
+                return Err(std::io::Error::new(std::io::ErrorKind::AddrInUse, "another instance is running"));
+            }
+            std::fs::remove_file(path)?;
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let listener = UnixListener::bind(path)?;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    /// Hands requests from every connection to the app as `Event::Control` until cancelled,
+    /// then removes the socket.
+    pub async fn serve(listener: UnixListener, path: PathBuf, tx: Sender<Event>, cancel_token: CancellationToken) {
+        loop {
+            tokio::select! {
+                connection = listener.accept() => {
+                    let Ok((stream, _)) = connection else {continue};
+                    tokio::spawn(handle_connection(stream, tx.clone(), cancel_token.clone()));
+                }
+                _ = cancel_token.cancelled() => break,
+            }
+        }
+        let _ = std::fs::remove_file(path);
+    }
+
+    async fn handle_connection(stream: UnixStream, tx: Sender<Event>, cancel_token: CancellationToken) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        loop {
+            let line = tokio::select! {
+                line = lines.next_line() => line,
+                _ = cancel_token.cancelled() => break,
+            };
+            let Ok(Some(line)) = line else {break};
+            if line.trim().is_empty() {continue}
+            let response = match serde_json::from_str::<ControlRequest>(&line) {
+                Ok(request) => {
+                    let (reply_tx, reply_rx) = oneshot::channel();
+                    match tx.send(Event::Control(request, reply_tx)).await {
+                        Ok(()) => reply_rx.await.unwrap_or_else(|_| ControlResponse::error("the timer is shutting down")),
+                        Err(_) => ControlResponse::error("the timer is shutting down"),
+                    }
+                }
+                Err(e) => ControlResponse::error(format!("invalid request: {e}")),
+            };
+            let mut json = serde_json::to_string(&response).expect("Responses should serialize");
+            json.push('\n');
+            if writer.write_all(json.as_bytes()).await.is_err() {break}
+        }
+    }
+
+    /// Sends one request to the instance listening on `path` and waits for its answer. Blocking,
+    /// for commands that run outside the TUI.
+    pub fn request(path: &Path, request: &ControlRequest) -> std::io::Result<ControlResponse> {
+        let stream = std::os::unix::net::UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(Duration::from_secs(1)))?;
+        let mut json = serde_json::to_string(request).expect("Requests should serialize");
+        json.push('\n');
+        (&stream).write_all(json.as_bytes())?;
+        let mut line = String::new();
+        std::io::BufReader::new(&stream).read_line(&mut line)?;
+        serde_json::from_str(&line).map_err(std::io::Error::other)
+    }
+
+    /// Starts listening in the background. `None` if the socket can't be set up, the timer
+    /// works without it.
+    pub async fn spawn(tx: Sender<Event>, cancel_token: CancellationToken) -> Option<tokio::task::JoinHandle<()>> {
+        let path = socket_path().ok()?;
+        let listener = bind(&path).await.ok()?;
+        Some(tokio::spawn(serve(listener, path, tx, cancel_token)))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::UnixStream;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::app::Event;
+
+    fn status() -> TimerStatus {
+        TimerStatus {
+            state: "work".to_string(),
+            phase: "Work".to_string(),
+            time_left: 1500,
+            duration: 1500,
+            iteration: 1,
+            total_iterations: 4,
+            running: true,
+            subject: None,
+            task: None,
+            interruptions: 0,
+        }
+    }
+
+    #[test]
+    fn format_status() {
+        let mut status = status();
+        status.time_left = 65;
+        assert_eq!(status.format(DEFAULT_STATUS_FORMAT), "Work 01:05 1/4");
+        status.running = false;
+        status.state = "long_break".to_string();
+        status.task = Some("write".to_string());
+        assert_eq!(status.format("{state}: {task} {paused} {unknown} {"), "Long Break: write paused {unknown} {");
+        status.phase = "Deep work".to_string();
+        assert_eq!(status.format("{phase}"), "Deep work");
+    }
+    #[test]
+    fn parse_requests() {
+        let parse = |json| serde_json::from_str::<ControlRequest>(json);
+        assert_eq!(parse(r#"{"command": "toggle"}"#).unwrap(), ControlRequest::Toggle);
+        assert_eq!(parse(r#"{"command": "extend"}"#).unwrap(), ControlRequest::Extend { seconds: EXTEND_TIME });
+        assert_eq!(parse(r#"{"command": "extend", "seconds": 60}"#).unwrap(), ControlRequest::Extend { seconds: 60 });
+        assert!(parse(r#"{"command": "explode"}"#).is_err());
+        let error = serde_json::to_string(&ControlResponse::error("nope")).unwrap();
+        assert_eq!(error, r#"{"ok":false,"error":"nope"}"#);
+    }
+    #[tokio::test]
+    async fn requests_become_events() {
+        let path = std::env::temp_dir().join(format!("romodoro-control-{}.sock", std::process::id()));
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let cancel_token = CancellationToken::new();
+        let listener = bind(&path).await.unwrap();
+        let server = tokio::spawn(serve(listener, path.clone(), tx, cancel_token.clone()));
+        assert!(bind(&path).await.is_err(), "a second instance can't take over the socket");
+        // stands in for App::run
+        tokio::spawn(async move {
+            while let Some(Event::Control(request, reply)) = rx.recv().await {
+                let mut status = status();
+                status.running = request != ControlRequest::Stop;
+                let _ = reply.send(ControlResponse::status(status));
+            }
+        });
+
+        let stream = UnixStream::connect(&path).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"{\"command\": \"stop\"}\n\nnot json\n").await.unwrap();
+        let stopped: ControlResponse = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert!(stopped.ok && !stopped.status.unwrap().running);
+        let invalid: ControlResponse = serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert!(!invalid.ok && invalid.error.unwrap().starts_with("invalid request"));
+        let client_path = path.clone();
+        let status = tokio::task::spawn_blocking(move || request(&client_path, &ControlRequest::Status)).await.unwrap();
+        assert!(status.unwrap().status.unwrap().running);
+
+        cancel_token.cancel();
+        server.await.unwrap();
+        assert!(!path.exists());
+    }
+}
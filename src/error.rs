@@ -11,6 +11,21 @@ pub enum SettingsError {
     #[error("There was an error with loading your data: {0}")]
     LoadError(String),
 
+    #[error("Invalid key binding in the config: {0}")]
+    KeymapError(String),
+
+    #[error("Invalid theme in the config: {0}")]
+    ThemeError(String),
+
+    #[error("Invalid clock font: {0}")]
+    FontError(String),
+
+    #[error("Invalid timer profile in the config: {0}")]
+    ProfileError(String),
+
+    #[error("Invalid timer preset: {0}")]
+    PresetError(String),
+
     #[error("Couldn't locate a suitable directory to keep your config in.")]
     HomeDirNotFound,
 
@@ -18,12 +33,43 @@ pub enum SettingsError {
     IO(#[from] io::Error),
 }
 #[derive(thiserror::Error, Debug)]
+pub enum StatsError {
+    #[error("There was an error with saving your session history: {0}")]
+    SaveError(String),
+
+    #[error("There was an error with loading your session history: {0}")]
+    LoadError(String),
+
+    #[error("Pixela request failed: {0}")]
+    PixelaError(String),
+
+    #[error("There was an error with exporting your session history: {0}")]
+    ExportError(String),
+
+    #[error("Couldn't import sessions: {0}")]
+    ImportError(String),
+}
+#[derive(thiserror::Error, Debug)]
+pub enum TaskError {
+    #[error("There was an error with saving your tasks: {0}")]
+    SaveError(String),
+
+    #[error("There was an error with loading your tasks: {0}")]
+    LoadError(String),
+}
+#[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("{0}")]
     IO(#[from] io::Error),
 
     #[error("Settings Error: {0}")]
     SettingsError(#[from] SettingsError),
+
+    #[error("Stats Error: {0}")]
+    StatsError(#[from] StatsError),
+
+    #[error("Task Error: {0}")]
+    TaskError(#[from] TaskError),
     
 }
 pub type Result<T> = std::result::Result<T,Error>;
@@ -0,0 +1,170 @@
+use std::{borrow::Cow, fmt::Write, fs, path::{Path, PathBuf}};
+
+use chrono::NaiveDate;
+
+use crate::error::{Result, StatsError};
+use crate::stats::{Session, SessionKind, SessionStore};
+
+const CSV_HEADER: &str = "kind,start,end,duration,iteration,subject,task,completed,interruptions";
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Which sessions to export. Every bound is optional and days are inclusive.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExportFilter {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub subject: Option<String>,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+impl ExportFilter {
+    pub fn matches(&self, session: &Session) -> bool {
+        let day = session.start.date_naive();
+        self.from.is_none_or(|from| day >= from)
+            && self.to.is_none_or(|to| day <= to)
+            && self.subject.as_ref().is_none_or(|subject| session.subject.as_ref() == Some(subject))
+    }
+    /// File name for an export of this filter, e.g. `sessions-from-2025-03-04-to-2025-03-10.csv`.
+    pub fn file_name(&self, format: ExportFormat) -> String {
+        let mut name = "sessions".to_string();
+        if let Some(from) = self.from {
+            let _ = write!(name, "-from-{from}");
+        }
+        if let Some(to) = self.to {
+            let _ = write!(name, "-to-{to}");
+        }
+        if let Some(subject) = &self.subject {
+            let subject: String = subject.chars().map(|c| if c.is_alphanumeric() {c} else {'_'}).collect();
+            let _ = write!(name, "-{subject}");
+        }
+        format!("{name}.{}", format.extension())
+    }
+}
+
+/// The sessions matching `filter`, oldest first.
+pub fn export(sessions: &[Session], filter: &ExportFilter, format: ExportFormat) -> Result<String> {
+    let mut sessions: Vec<&Session> = sessions.iter().filter(|s| filter.matches(s)).collect();
+    sessions.sort_by_key(|s| s.start);
+    match format {
+        ExportFormat::Csv => Ok(to_csv(&sessions)),
+        ExportFormat::Json => {
+            serde_json::to_string_pretty(&sessions).map_err(|e| StatsError::ExportError(e.to_string()).into())
+        }
+    }
+}
+
+/// Writes the export to `path` and returns how many sessions went in.
+pub fn export_to_file(store: &SessionStore, filter: &ExportFilter, format: ExportFormat, path: &Path) -> Result<usize> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        if !dir.exists() {fs::create_dir_all(dir)?}
+    }
+    fs::write(path, export(store.get_sessions(), filter, format)?)?;
+    Ok(store.get_sessions().iter().filter(|s| filter.matches(s)).count())
+}
+
+/// Where the stats tab puts exports, an `exports` folder next to the session history.
+pub fn export_dir(store: &SessionStore) -> PathBuf {
+    store.get_path().parent().map(|dir| dir.join("exports")).unwrap_or_else(|| PathBuf::from("exports"))
+}
+
+fn to_csv(sessions: &[&Session]) -> String {
+    let mut csv = format!("{CSV_HEADER}\n");
+    for session in sessions {
+        let kind = match session.kind {
+            SessionKind::Work => "work",
+            SessionKind::Break => "break",
+            SessionKind::LongBreak => "long_break",
+        };
+        let _ = writeln!(
+            csv,
+            "{},{},{},{},{},{},{},{},{}",
+            kind,
+            session.start.to_rfc3339(),
+            session.end.to_rfc3339(),
+            session.duration,
+            session.iteration,
+            csv_field(session.subject.as_deref().unwrap_or_default()),
+            csv_field(session.task.as_deref().unwrap_or_default()),
+            session.completed,
+            session.interruptions.len(),
+        );
+    }
+    csv
+}
+
+/// Quotes fields that spreadsheets would otherwise split up.
+fn csv_field(value: &str) -> Cow<'_, str> {
+    match value.contains([',', '"', '\n', '\r']) {
+        true => Cow::Owned(format!("\"{}\"", value.replace('"', "\"\""))),
+        false => Cow::Borrowed(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, TimeZone};
+
+    use super::*;
+
+    fn session(day: u32, kind: SessionKind, subject: Option<&str>) -> Session {
+        let start = Local.with_ymd_and_hms(2025, 3, day, 9, 0, 0).unwrap();
+        Session {
+            kind,
+            start,
+            end: start + chrono::Duration::minutes(25),
+            duration: 1500,
+            iteration: 1,
+            subject: subject.map(String::from),
+            task: None,
+            interruptions: Vec::new(),
+            completed: true,
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let sessions = [
+            session(3, SessionKind::Work, Some("rust")),
+            session(4, SessionKind::Break, None),
+            session(5, SessionKind::Work, Some("maths")),
+        ];
+        let count = |filter: ExportFilter| sessions.iter().filter(|s| filter.matches(s)).count();
+        assert_eq!(count(ExportFilter::default()), 3);
+        let day = |d| NaiveDate::from_ymd_opt(2025, 3, d);
+        assert_eq!(count(ExportFilter { from: day(4), ..Default::default() }), 2);
+        assert_eq!(count(ExportFilter { from: day(4), to: day(4), ..Default::default() }), 1);
+        assert_eq!(count(ExportFilter { subject: Some("rust".to_string()), ..Default::default() }), 1);
+        let filter = ExportFilter { from: day(1), to: day(9), subject: Some("a, b".to_string()) };
+        assert_eq!(filter.file_name(ExportFormat::Json), "sessions-from-2025-03-01-to-2025-03-09-a__b.json");
+    }
+    #[test]
+    fn csv_and_json() {
+        let mut quoted = session(5, SessionKind::Work, Some("say \"hi\", then go"));
+        quoted.completed = false;
+        let sessions = [quoted, session(3, SessionKind::LongBreak, None)];
+
+        let csv = export(&sessions, &ExportFilter::default(), ExportFormat::Csv).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], CSV_HEADER);
+        assert!(lines[1].starts_with("long_break,2025-03-03T09:00:00"));
+        assert!(lines[1].ends_with(",1500,1,,,true,0"));
+        assert!(lines[2].ends_with(",\"say \"\"hi\"\", then go\",,false,0"));
+
+        let json = export(&sessions, &ExportFilter::default(), ExportFormat::Json).unwrap();
+        let parsed: Vec<Session> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, [sessions[1].clone(), sessions[0].clone()]);
+    }
+}
@@ -0,0 +1,202 @@
+use std::{fs, path::Path};
+
+use crate::error::SettingsError;
+use crate::settings::UISettings;
+
+/// Built-in fonts from biggest to smallest, `auto` takes the first one that fits.
+pub const BUILTIN_FONTS: [&str; 3] = ["block", "classic", "compact"];
+/// Glyph order in fonts and font files.
+const GLYPHS: [char; 11] = ['0', '1', '2', '3', '4', '5', '6', '7', '8', '9', ':'];
+
+/// Big digits for the clock.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Font {
+    /// Rows of each glyph in `GLYPHS` order, all glyphs are equally tall.
+    glyphs: Vec<Vec<String>>,
+    /// Columns between glyphs.
+    spacing: usize,
+}
+
+impl Font {
+    pub fn builtin(name: &str) -> Option<Font> {
+        match name {
+            "compact" => Some(Font::from_rows(&COMPACT, 1)),
+            "classic" => Some(Font::from_rows(&CLASSIC, 2)),
+            "block" => Some(Font::from_rows(&CLASSIC, 2).scaled(2)),
+            _ => None,
+        }
+    }
+    /// The font `settings` ask for, or the biggest built-in one that fits `text` in `width` x `height`.
+    pub fn select(settings: &UISettings, text: &str, width: u16, height: u16) -> Font {
+        match settings.font.as_str() {
+            "custom" => settings.custom_font.clone().unwrap_or_default(),
+            name => Font::builtin(name).unwrap_or_else(|| Font::fitting(text, width, height)),
+        }
+    }
+    fn fitting(text: &str, width: u16, height: u16) -> Font {
+        BUILTIN_FONTS
+            .iter()
+            .filter_map(|name| Font::builtin(name))
+            .find(|font| {
+                let (text_width, text_height) = font.size(text);
+                text_width <= width && text_height <= height
+            })
+            .unwrap_or_else(|| Font::builtin("compact").expect("Compact font is built in"))
+    }
+    fn from_rows(glyphs: &[&str; 11], spacing: usize) -> Font {
+        Font { glyphs: glyphs.iter().map(|glyph| glyph.lines().map(str::to_string).collect()).collect(), spacing }
+    }
+    fn scaled(self, factor: usize) -> Font {
+        let glyphs = self
+            .glyphs
+            .iter()
+            .map(|rows| {
+                rows.iter()
+                    .flat_map(|row| {
+                        let wide: String = row.chars().flat_map(|c| std::iter::repeat_n(c, factor)).collect();
+                        std::iter::repeat_n(wide, factor)
+                    })
+                    .collect()
+            })
+            .collect();
+        Font { glyphs, spacing: self.spacing * factor }
+    }
+    pub fn height(&self) -> usize {
+        self.glyphs[0].len()
+    }
+    /// Columns and rows `text` takes up.
+    pub fn size(&self, text: &str) -> (u16, u16) {
+        let width = self.render(text).lines().next().map_or(0, |row| row.chars().count());
+        (width as u16, self.height() as u16)
+    }
+    /// Draws the digits and colons of `text`, anything else is skipped.
+    pub fn render(&self, text: &str) -> String {
+        let mut output = vec![String::new(); self.height()];
+        let glyphs: Vec<&Vec<String>> = text
+            .chars()
+            .filter_map(|c| GLYPHS.iter().position(|glyph| *glyph == c))
+            .map(|index| &self.glyphs[index])
+            .collect();
+        for (i, glyph) in glyphs.iter().enumerate() {
+            for (row, line) in glyph.iter().enumerate() {
+                output[row].push_str(line);
+                if i + 1 < glyphs.len() {
+                    output[row].push_str(&" ".repeat(self.spacing));
+                }
+            }
+        }
+        output.join("\n")
+    }
+    /// Loads a font file: each glyph starts with a `[0]` ... `[9]` or `[:]` line followed by its rows.
+    /// Short rows are padded, so trailing spaces don't matter.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Font, SettingsError> {
+        let path = path.as_ref();
+        let data = fs::read_to_string(path)
+            .map_err(|e| SettingsError::FontError(format!("couldn't read {}: {e}", path.display())))?;
+        Font::parse(&data).map_err(|e| SettingsError::FontError(format!("{} in {}", e, path.display())))
+    }
+    fn parse(data: &str) -> Result<Font, String> {
+        let mut glyphs: Vec<Option<Vec<String>>> = vec![None; GLYPHS.len()];
+        let mut current: Option<usize> = None;
+        for line in data.lines() {
+            let marker = line
+                .trim()
+                .strip_prefix('[')
+                .and_then(|rest| rest.strip_suffix(']'))
+                .and_then(|glyph| GLYPHS.iter().position(|c| glyph.len() == 1 && glyph.starts_with(*c)));
+            match (marker, current) {
+                (Some(index), _) => {
+                    if glyphs[index].is_some() {
+                        return Err(format!("glyph [{}] is defined twice", GLYPHS[index]));
+                    }
+                    glyphs[index] = Some(Vec::new());
+                    current = Some(index);
+                }
+                (None, Some(index)) => glyphs[index].get_or_insert_with(Vec::new).push(line.trim_end().to_string()),
+                (None, None) if line.trim().is_empty() => {}
+                (None, None) => return Err("rows before the first glyph marker".to_string()),
+            }
+        }
+        let mut font = Vec::new();
+        for (index, glyph) in glyphs.into_iter().enumerate() {
+            let mut rows = glyph.ok_or_else(|| format!("glyph [{}] is missing", GLYPHS[index]))?;
+            while rows.last().is_some_and(|row| row.is_empty()) {
+                rows.pop();
+            }
+            let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or(0);
+            font.push(rows.iter().map(|row| format!("{row:<width$}")).collect::<Vec<_>>());
+        }
+        let height = font.iter().map(Vec::len).max().unwrap_or(0);
+        if height == 0 {
+            return Err("all glyphs are empty".to_string());
+        }
+        if let Some(index) = font.iter().position(|rows| rows.len() != height) {
+            return Err(format!("glyph [{}] isn't {} rows tall like the others", GLYPHS[index], height));
+        }
+        Ok(Font { glyphs: font, spacing: 1 })
+    }
+}
+impl Default for Font {
+    fn default() -> Self {
+        Font::builtin("classic").expect("Classic font is built in")
+    }
+}
+
+const COMPACT: [&str; 11] = [
+    "█▀█\n█ █\n▀▀▀", // 0
+    "▀█ \n █ \n▀▀▀", // 1
+    "▀▀█\n█▀▀\n▀▀▀", // 2
+    "▀▀█\n ▀█\n▀▀▀", // 3
+    "█ █\n▀▀█\n  ▀", // 4
+    "█▀▀\n▀▀█\n▀▀▀", // 5
+    "█▀▀\n█▀█\n▀▀▀", // 6
+    "▀▀█\n  █\n  ▀", // 7
+    "█▀█\n█▀█\n▀▀▀", // 8
+    "█▀█\n▀▀█\n▀▀▀", // 9
+    " ▄ \n   \n ▀ ", // :
+];
+const CLASSIC: [&str; 11] = [
+    "  ███  \n █   █ \n█     █\n█     █\n█     █\n █   █ \n  ███  ", // 0
+    "   █   \n  ██   \n █ █   \n   █   \n   █   \n   █   \n ████  ", // 1
+    " ███   \n█   █  \n    █  \n   █   \n  █    \n █     \n█████  ", // 2
+    " ███   \n█   █  \n    █  \n  ██   \n    █  \n█   █  \n ███   ", // 3
+    "   ██  \n  █ █  \n █  █  \n█   █  \n█████  \n    █  \n    █  ", // 4
+    "█████  \n█      \n████   \n    █  \n    █  \n█   █  \n ███   ", // 5
+    "  ███  \n █     \n█      \n█ ███  \n█    █ \n █   █ \n  ███  ", // 6
+    "█████  \n    █  \n   █   \n  █    \n █     \n █     \n █     ", // 7
+    "  ███  \n █   █ \n █   █ \n  ███  \n █   █ \n █   █ \n  ███  ", // 8
+    "  ███  \n █   █ \n █   █ \n  ████ \n     █ \n    ██ \n  ███  ", // 9
+    "        \n   █    \n   █    \n        \n   █    \n   █    \n        ", // :
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_picks_biggest_that_fits() {
+        let text = "00:25:00";
+        let block = Font::builtin("block").unwrap();
+        let classic = Font::builtin("classic").unwrap();
+        assert_eq!(block.size(text), (144, 14));
+        assert_eq!(classic.size(text), (72, 7));
+        assert_eq!(Font::fitting(text, 200, 40), block);
+        assert_eq!(Font::fitting(text, 100, 10), classic);
+        assert_eq!(Font::fitting(text, 40, 5).height(), 3);
+        assert_eq!(Font::fitting(text, 10, 1).height(), 3);
+    }
+    #[test]
+    fn parse_font_file() {
+        let mut file = String::new();
+        for glyph in GLYPHS {
+            file.push_str(&format!("[{glyph}]\n{glyph}{glyph}\n{glyph}\n\n"));
+        }
+        let font = Font::parse(&file).unwrap();
+        assert_eq!(font.render("1:2"), "11 :: 22\n1  :  2 ");
+
+        assert!(Font::parse(&file.replace("[:]", "[0]")).unwrap_err().contains("glyph [0] is defined twice"));
+        assert!(Font::parse(&file.replace("[:]\n", "[:]\n::\n")).unwrap_err().contains("[0] isn't 3 rows"));
+        let missing = file.split("[:]").next().unwrap();
+        assert!(Font::parse(missing).unwrap_err().contains("glyph [:] is missing"));
+    }
+}
@@ -0,0 +1,239 @@
+use std::io::{self, Write};
+
+use tokio_util::sync::CancellationToken;
+
+use crate::romodoro::{Pomodoro, Transition};
+use crate::timer::{PomodoroState, Timer};
+
+const PROGRESS_WIDTH: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// A status line per transition and one progress line that is redrawn in place.
+    Text,
+    /// One JSON object per line.
+    Json,
+}
+
+/// How a headless run ended.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Outcome {
+    Completed,
+    Interrupted,
+}
+
+/// Runs a whole cycle without the TUI, printing progress to `out` until it completes or Ctrl-C is pressed.
+pub async fn run(
+    pomodoro: &mut Pomodoro,
+    time_rx: &mut tokio::sync::mpsc::Receiver<i64>,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> io::Result<Outcome> {
+    let cancel_token = CancellationToken::new();
+    pomodoro.create_countdown(cancel_token.clone()).await;
+    pomodoro.cycle().await;
+    print_start(pomodoro, format, out)?;
+
+    let outcome = loop {
+        tokio::select! {
+            time = time_rx.recv() => {
+                let Some(time) = time else {break Outcome::Interrupted};
+                match pomodoro.handle_timer_responses(time).await {
+                    Some(Transition::CycleComplete { ended }) => {
+                        print_complete(pomodoro, ended, format, out)?;
+                        break Outcome::Completed;
+                    }
+                    Some(Transition::Next { ended, started }) => print_transition(pomodoro, ended, started, format, out)?,
+                    None => print_progress(pomodoro, format, out)?,
+                }
+            }
+            _ = tokio::signal::ctrl_c() => {
+                if format == OutputFormat::Text {
+                    writeln!(out)?;
+                }
+                break Outcome::Interrupted;
+            }
+        }
+    };
+    cancel_token.cancel();
+    Ok(outcome)
+}
+
+fn format_time(time: i64) -> String {
+    let time = time.max(0);
+    format!("{:02}:{:02}", time / 60, time % 60)
+}
+
+fn print_start(pomodoro: &Pomodoro, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+    let timer = &pomodoro.timer;
+    let state = timer.get_current_state();
+    match format {
+        OutputFormat::Text => writeln!(
+            out,
+            "Started {} ({}) {}/{}",
+            timer.get_state_label(),
+            format_time(Timer::get_duration(&state)),
+            timer.get_iteration(),
+            timer.get_total_iterations()
+        ),
+        OutputFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::json!({
+                "event": "start",
+                "state": state.to_string(),
+                "phase": timer.get_state_label(),
+                "duration": Timer::get_duration(&state),
+                "iteration": timer.get_iteration(),
+                "total_iterations": timer.get_total_iterations(),
+                "subject": timer.get_subject(),
+            })
+        ),
+    }?;
+    out.flush()
+}
+
+fn print_progress(pomodoro: &Pomodoro, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+    let timer = &pomodoro.timer;
+    let state = timer.get_current_state();
+    let time_left = timer.get_timeleft();
+    match format {
+        OutputFormat::Text => {
+            let duration = Timer::get_duration(&state).max(1);
+            let done = ((duration - time_left.max(0)) * PROGRESS_WIDTH as i64 / duration) as usize;
+            write!(
+                out,
+                "\r\x1b[2K{} {}/{} {} [{}{}]",
+                timer.get_state_label(),
+                timer.get_iteration(),
+                timer.get_total_iterations(),
+                format_time(time_left),
+                "#".repeat(done),
+                "-".repeat(PROGRESS_WIDTH - done.min(PROGRESS_WIDTH))
+            )
+        }
+        OutputFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::json!({
+                "event": "tick",
+                "state": state.to_string(),
+                "phase": timer.get_state_label(),
+                "time_left": time_left,
+                "iteration": timer.get_iteration(),
+                "total_iterations": timer.get_total_iterations(),
+            })
+        ),
+    }?;
+    out.flush()
+}
+
+fn print_transition(
+    pomodoro: &Pomodoro,
+    ended: PomodoroState,
+    started: PomodoroState,
+    format: OutputFormat,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    let timer = &pomodoro.timer;
+    // profiles name their phases, the timer is already on the one that started
+    let ended_label = timer
+        .get_phase_at(timer.get_iteration().saturating_sub(1))
+        .map_or_else(|| ended.to_string(), |phase| phase.label.clone());
+    match format {
+        OutputFormat::Text => writeln!(
+            out,
+            "\r\x1b[2K{} finished, now {} ({}) {}/{}",
+            ended_label,
+            timer.get_state_label(),
+            format_time(Timer::get_duration(&started)),
+            timer.get_iteration(),
+            timer.get_total_iterations()
+        ),
+        OutputFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::json!({
+                "event": "transition",
+                "ended": ended.to_string(),
+                "started": started.to_string(),
+                "phase": timer.get_state_label(),
+                "duration": Timer::get_duration(&started),
+                "iteration": timer.get_iteration(),
+                "total_iterations": timer.get_total_iterations(),
+            })
+        ),
+    }?;
+    out.flush()
+}
+
+fn print_complete(pomodoro: &Pomodoro, ended: PomodoroState, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+    let timer = &pomodoro.timer;
+    match format {
+        OutputFormat::Text => writeln!(
+            out,
+            "\r\x1b[2K{} finished, cycle of {} {} complete",
+            timer.get_phase().map_or_else(|| ended.to_string(), |phase| phase.label.clone()),
+            timer.get_total_iterations(),
+            if timer.get_phases().is_empty() {"iterations"} else {"phases"}
+        ),
+        OutputFormat::Json => writeln!(
+            out,
+            "{}",
+            serde_json::json!({
+                "event": "complete",
+                "ended": ended.to_string(),
+                "total_iterations": timer.get_total_iterations(),
+            })
+        ),
+    }?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::{notify::{tests::FakeBackend, NotificationKind, Notifier}, settings::SettingsTab, stats::SessionStore};
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_whole_cycle() {
+        let mut settings = SettingsTab::default();
+        settings.timer_settings.work_time = 3;
+        settings.timer_settings.break_time = 2;
+        settings.timer_settings.iterations = 2;
+        settings.timer_settings.long_break_interval = 0;
+        let settings = Rc::new(RefCell::new(settings));
+        let path = std::env::temp_dir().join(format!("romodoro-headless-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let stats = Rc::new(RefCell::new(SessionStore::from_path(&path).unwrap()));
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel(4);
+        let (tx_commands, rx_commands) = tokio::sync::mpsc::channel(4);
+        let mut pomodoro = Pomodoro::new(tx, rx_commands, tx_commands, settings, stats.clone());
+        let notifications = FakeBackend::default();
+        let mut notifier = Notifier::default();
+        notifier.add_backend(Box::new(notifications.clone()));
+        pomodoro.set_notifier(notifier);
+
+        let mut out = Vec::new();
+        let outcome = run(&mut pomodoro, &mut rx, OutputFormat::Json, &mut out).await.unwrap();
+        assert_eq!(outcome, Outcome::Completed);
+
+        let events: Vec<serde_json::Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .filter(|event: &serde_json::Value| event["event"] != "tick")
+            .collect();
+        let names: Vec<&str> = events.iter().map(|e| e["event"].as_str().unwrap()).collect();
+        assert_eq!(names, ["start", "transition", "transition", "complete"]);
+        assert_eq!(events[1]["started"], "Break");
+        assert_eq!(events[2]["iteration"], 2);
+        assert_eq!(stats.borrow().get_sessions().len(), 3);
+        let kinds: Vec<NotificationKind> = notifications.0.borrow().iter().map(|n| n.kind).collect();
+        assert_eq!(kinds, [NotificationKind::WorkEnded, NotificationKind::BreakEnded, NotificationKind::CycleComplete]);
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,210 @@
+use std::{fmt::Display, process::Stdio, time::Duration};
+
+use serde::*;
+use tokio::sync::mpsc::Sender;
+
+use crate::app::Event;
+use crate::timer::{PomodoroState, Timer};
+
+/// Shell commands run on timer events, e.g. to toggle do-not-disturb.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookSettings {
+    pub on_work_start: Option<String>,
+    pub on_work_end: Option<String>,
+    pub on_break_start: Option<String>,
+    pub on_break_end: Option<String>,
+    pub on_cycle_complete: Option<String>,
+    pub on_pause: Option<String>,
+    /// Seconds a hook may run before it's killed.
+    pub timeout: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HookEvent {
+    /// Also fires when a paused work interval is resumed.
+    WorkStart,
+    WorkEnd,
+    BreakStart,
+    BreakEnd,
+    CycleComplete,
+    Pause,
+}
+
+/// One command ready to run, with the state it describes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hook {
+    event: HookEvent,
+    command: String,
+    env: Vec<(&'static str, String)>,
+    timeout: Duration,
+}
+
+/// A hook that exited with an error, couldn't start or timed out.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookFailure {
+    pub event: HookEvent,
+    pub reason: String,
+}
+
+impl Default for HookSettings {
+    fn default() -> Self {
+        HookSettings {
+            on_work_start: None,
+            on_work_end: None,
+            on_break_start: None,
+            on_break_end: None,
+            on_cycle_complete: None,
+            on_pause: None,
+            timeout: 10,
+        }
+    }
+}
+
+impl HookSettings {
+    pub fn command(&self, event: HookEvent) -> Option<&str> {
+        let command = match event {
+            HookEvent::WorkStart => &self.on_work_start,
+            HookEvent::WorkEnd => &self.on_work_end,
+            HookEvent::BreakStart => &self.on_break_start,
+            HookEvent::BreakEnd => &self.on_break_end,
+            HookEvent::CycleComplete => &self.on_cycle_complete,
+            HookEvent::Pause => &self.on_pause,
+        };
+        command.as_deref().filter(|command| !command.trim().is_empty())
+    }
+}
+
+impl HookEvent {
+    /// Name of the config key, also passed as `ROMODORO_HOOK`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HookEvent::WorkStart => "on_work_start",
+            HookEvent::WorkEnd => "on_work_end",
+            HookEvent::BreakStart => "on_break_start",
+            HookEvent::BreakEnd => "on_break_end",
+            HookEvent::CycleComplete => "on_cycle_complete",
+            HookEvent::Pause => "on_pause",
+        }
+    }
+    pub fn start_of(state: PomodoroState) -> HookEvent {
+        match state {
+            PomodoroState::Work(_) => HookEvent::WorkStart,
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_) => HookEvent::BreakStart,
+        }
+    }
+    pub fn end_of(state: PomodoroState) -> HookEvent {
+        match state {
+            PomodoroState::Work(_) => HookEvent::WorkEnd,
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_) => HookEvent::BreakEnd,
+        }
+    }
+}
+
+impl Display for HookFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} hook {}", self.event.name(), self.reason)
+    }
+}
+
+impl Hook {
+    /// The configured hook for `event`, `None` if there isn't one. `state` and `iteration` are the
+    /// interval the event is about, which for end events isn't the timer's current one anymore.
+    pub fn new(settings: &HookSettings, event: HookEvent, state: PomodoroState, iteration: u8, timer: &Timer) -> Option<Hook> {
+        let command = settings.command(event)?.to_string();
+        let state_name = match state {
+            PomodoroState::Work(_) => "work",
+            PomodoroState::Break(_) => "break",
+            PomodoroState::LongBreak(_) => "long_break",
+        };
+        let time_left = match event {
+            HookEvent::WorkStart | HookEvent::BreakStart | HookEvent::Pause => timer.get_timeleft().max(0),
+            HookEvent::WorkEnd | HookEvent::BreakEnd | HookEvent::CycleComplete => 0,
+        };
+        let env = vec![
+            ("ROMODORO_HOOK", event.name().to_string()),
+            ("ROMODORO_STATE", state_name.to_string()),
+            ("ROMODORO_PHASE", timer.get_phase_at(iteration).map_or_else(|| state.to_string(), |phase| phase.label.clone())),
+            ("ROMODORO_SUBJECT", timer.get_subject().unwrap_or_default()),
+            ("ROMODORO_ITERATION", iteration.to_string()),
+            ("ROMODORO_TOTAL_ITERATIONS", timer.get_total_iterations().to_string()),
+            ("ROMODORO_DURATION", Timer::get_duration(&state).to_string()),
+            ("ROMODORO_TIME_LEFT", time_left.to_string()),
+        ];
+        Some(Hook { event, command, env, timeout: Duration::from_secs(settings.timeout.max(1)) })
+    }
+    /// Runs the command with `sh -c`, killing it once it runs out of time.
+    pub async fn run(&self) -> std::result::Result<(), HookFailure> {
+        let failure = |reason: String| HookFailure { event: self.event, reason };
+        let child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.command)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| failure(format!("couldn't start: {e}")))?;
+        let output = match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(output) => output.map_err(|e| failure(format!("failed: {e}")))?,
+            Err(_) => return Err(failure(format!("timed out after {}s", self.timeout.as_secs()))),
+        };
+        if output.status.success() {return Ok(())}
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(failure(match stderr.lines().find(|line| !line.trim().is_empty()) {
+            Some(line) => format!("failed ({}): {}", output.status, line.trim()),
+            None => format!("failed ({})", output.status),
+        }))
+    }
+    /// Runs hooks one after another in the background, so an end hook is done before the next
+    /// start hook. Failures go to the app as `Event::HookFailed`, or to stderr without one.
+    pub fn spawn_all(hooks: Vec<Hook>, reporter: Option<Sender<Event>>) {
+        if hooks.is_empty() {return}
+        tokio::spawn(async move {
+            for hook in hooks {
+                let Err(failure) = hook.run().await else {continue};
+                match &reporter {
+                    Some(tx) => {
+                        let _ = tx.send(Event::HookFailed(failure)).await;
+                    }
+                    None => eprintln!("{failure}"),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    fn hook(command: &str) -> Hook {
+        Hook {
+            event: HookEvent::WorkEnd,
+            command: command.to_string(),
+            env: vec![("ROMODORO_STATE", "work".to_string())],
+            timeout: Duration::from_millis(200),
+        }
+    }
+
+    #[test]
+    fn commands_by_event() {
+        let settings = HookSettings {
+            on_work_end: Some("notify-send done".to_string()),
+            on_pause: Some(" ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(settings.command(HookEvent::end_of(PomodoroState::Work(1500))), Some("notify-send done"));
+        assert_eq!(settings.command(HookEvent::Pause), None);
+        assert_eq!(HookEvent::start_of(PomodoroState::LongBreak(900)), HookEvent::BreakStart);
+    }
+    #[tokio::test]
+    async fn failures_are_reported() {
+        assert_eq!(hook("test \"$ROMODORO_STATE\" = work").run().await, Ok(()));
+        let failure = hook("echo no vpn >&2; exit 3").run().await.unwrap_err();
+        assert_eq!(failure.to_string(), "on_work_end hook failed (exit status: 3): no vpn");
+        let failure = hook("sleep 5").run().await.unwrap_err();
+        assert!(failure.reason.starts_with("timed out"));
+    }
+}
@@ -0,0 +1,425 @@
+use std::{collections::BTreeMap, path::Path};
+
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone};
+use serde::*;
+use serde_json::Value;
+
+use crate::error::{Result, StatsError};
+use crate::stats::{Session, SessionKind, SessionStore};
+
+/// Timestamp formats tried after RFC 3339 when the mapping doesn't name one.
+const TIME_FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M", "%d/%m/%Y %H:%M"];
+
+/// One row of another tool's export, field name to value.
+pub type Record = BTreeMap<String, String>;
+
+/// Reads another tool's export into records.
+pub trait Importer {
+    /// Records with the row they come from, counting like `SkippedRow::row`.
+    fn records(&self, data: &str) -> Result<Vec<(usize, Record)>>;
+}
+
+/// CSV with a header row.
+#[derive(Debug, Clone)]
+pub struct CsvImporter {
+    pub delimiter: char,
+}
+
+/// A JSON array of objects, or an object holding one. Nested fields are named like `project.name`.
+#[derive(Debug, Clone, Default)]
+pub struct JsonImporter;
+
+#[derive(Debug, Clone, Copy, PartialEq, clap::ValueEnum)]
+pub enum ImportFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum DurationUnit {
+    #[default]
+    Seconds,
+    Minutes,
+}
+
+/// Which fields of an export hold what, kept under `[import_mappings.<name>]` in the config.
+/// The defaults read romodoro's own exports.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub start: String,
+    /// Needed unless there is a duration.
+    pub end: Option<String>,
+    pub duration: Option<String>,
+    pub duration_unit: DurationUnit,
+    /// Work, break or long break. Rows count as work without it.
+    pub kind: Option<String>,
+    pub subject: Option<String>,
+    pub task: Option<String>,
+    pub completed: Option<String>,
+    /// `chrono` format for timestamps that aren't RFC 3339 or one of the common ones.
+    pub time_format: Option<String>,
+    /// CSV only.
+    pub delimiter: char,
+}
+
+/// A row that couldn't be imported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SkippedRow {
+    /// Counting from 1, the CSV header isn't a row.
+    pub row: usize,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportReport {
+    pub imported: usize,
+    /// Rows overlapping a session that's already recorded.
+    pub duplicates: usize,
+    pub skipped: Vec<SkippedRow>,
+}
+
+impl ColumnMapping {
+    /// Sets a field from a `field=column` pair as given on the command line.
+    pub fn set(&mut self, pair: &str) -> std::result::Result<(), String> {
+        let (field, column) = pair.split_once('=').ok_or(format!("expected field=column, got \"{pair}\""))?;
+        let column = column.trim().to_string();
+        match field.trim() {
+            "start" => self.start = column,
+            "end" => self.end = Some(column),
+            "duration" => self.duration = Some(column),
+            "kind" => self.kind = Some(column),
+            "subject" => self.subject = Some(column),
+            "task" => self.task = Some(column),
+            "completed" => self.completed = Some(column),
+            field => return Err(format!("unknown field \"{field}\"")),
+        }
+        Ok(())
+    }
+    fn session(&self, record: &Record) -> std::result::Result<Session, String> {
+        let field = |column: &Option<String>| {
+            column.as_ref().and_then(|column| record.get(column)).map(|value| value.trim()).filter(|value| !value.is_empty())
+        };
+        let start = record.get(&self.start).map(|value| value.trim()).filter(|value| !value.is_empty());
+        let start = self.time(start.ok_or(format!("no start in \"{}\"", self.start))?)?;
+        let end = field(&self.end).map(|end| self.time(end)).transpose()?;
+        let duration = field(&self.duration).map(|duration| self.duration(duration)).transpose()?;
+        let (end, duration) = match (end, duration) {
+            (end, Some(duration)) => (end.unwrap_or(start + chrono::Duration::seconds(duration)), duration),
+            (Some(end), None) => (end, (end - start).num_seconds()),
+            (None, None) => return Err("no end or duration".to_string()),
+        };
+        if duration <= 0 || end < start {
+            return Err("session doesn't last any time".to_string());
+        }
+        let kind = match field(&self.kind).map(|kind| kind.to_lowercase().replace(['_', '-'], " ")) {
+            None => SessionKind::Work,
+            Some(kind) => match kind.as_str() {
+                "work" | "pomodoro" | "focus" => SessionKind::Work,
+                "break" | "short break" => SessionKind::Break,
+                "long break" | "longbreak" => SessionKind::LongBreak,
+                _ => return Err(format!("unknown kind \"{kind}\"")),
+            },
+        };
+        let completed = match field(&self.completed).map(str::to_lowercase).as_deref() {
+            None | Some("true" | "yes" | "1") => true,
+            Some("false" | "no" | "0") => false,
+            Some(other) => return Err(format!("completed should be true or false, got \"{other}\"")),
+        };
+        Ok(Session {
+            kind,
+            start,
+            end,
+            duration,
+            iteration: 1,
+            subject: field(&self.subject).map(String::from),
+            task: field(&self.task).map(String::from),
+            interruptions: Vec::new(),
+            completed,
+        })
+    }
+    fn time(&self, value: &str) -> std::result::Result<DateTime<Local>, String> {
+        if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+            return Ok(time.with_timezone(&Local));
+        }
+        if let Ok(seconds) = value.parse::<i64>() {
+            // exports use seconds or milliseconds since the epoch
+            let time = match seconds.abs() > 100_000_000_000 {
+                true => DateTime::from_timestamp_millis(seconds),
+                false => DateTime::from_timestamp(seconds, 0),
+            };
+            return time.map(|time| time.with_timezone(&Local)).ok_or(format!("timestamp {value} is out of range"));
+        }
+        self.time_format
+            .iter()
+            .map(String::as_str)
+            .chain(TIME_FORMATS)
+            .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+            .ok_or(format!("can't read the time \"{value}\""))
+    }
+    /// A number in `duration_unit`, or `h:mm:ss` / `mm:ss`.
+    fn duration(&self, value: &str) -> std::result::Result<i64, String> {
+        let invalid = || format!("can't read the duration \"{value}\"");
+        if value.contains(':') {
+            return value
+                .split(':')
+                .try_fold(0, |total, part| part.parse::<i64>().map(|n| total * 60 + n))
+                .map_err(|_| invalid());
+        }
+        let number: f64 = value.parse().map_err(|_| invalid())?;
+        Ok(match self.duration_unit {
+            DurationUnit::Seconds => number,
+            DurationUnit::Minutes => number * 60.0,
+        }
+        .round() as i64)
+    }
+}
+
+impl Importer for CsvImporter {
+    fn records(&self, data: &str) -> Result<Vec<(usize, Record)>> {
+        let mut rows = parse_csv(data, self.delimiter)?.into_iter();
+        let header = rows.next().unwrap_or_default();
+        // numbered before blank rows are dropped, so skipped rows can be found in the file
+        Ok(rows
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|value| !value.trim().is_empty()))
+            .map(|(i, row)| (i + 1, header.iter().map(|name| name.trim().to_string()).zip(row).collect()))
+            .collect())
+    }
+}
+
+impl Importer for JsonImporter {
+    fn records(&self, data: &str) -> Result<Vec<(usize, Record)>> {
+        let json: Value = serde_json::from_str(data).map_err(|e| StatsError::ImportError(e.to_string()))?;
+        let rows = match json {
+            Value::Array(rows) => rows,
+            Value::Object(object) => object
+                .into_iter()
+                .find_map(|(_, value)| match value {
+                    Value::Array(rows) => Some(rows),
+                    _ => None,
+                })
+                .ok_or(StatsError::ImportError("no list of sessions in the file".to_string()))?,
+            _ => return Err(StatsError::ImportError("expected a list of sessions".to_string()).into()),
+        };
+        Ok(rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, row)| {
+                let mut record = Record::new();
+                flatten("", row, &mut record);
+                (i + 1, record)
+            })
+            .collect())
+    }
+}
+
+/// Reads `data` with `importer` and adds the sessions to `store`, leaving out ones that overlap
+/// recorded sessions or each other.
+pub fn import(store: &mut SessionStore, importer: &dyn Importer, mapping: &ColumnMapping, data: &str) -> Result<ImportReport> {
+    let mut report = ImportReport::default();
+    let mut sessions = Vec::new();
+    for (row, record) in importer.records(data)? {
+        match mapping.session(&record) {
+            Ok(session) => sessions.push(session),
+            Err(reason) => report.skipped.push(SkippedRow { row, reason }),
+        }
+    }
+    let total = sessions.len();
+    report.imported = store.merge(sessions);
+    report.duplicates = total - report.imported;
+    Ok(report)
+}
+
+impl CsvImporter {
+    pub fn new(mapping: &ColumnMapping) -> CsvImporter {
+        CsvImporter { delimiter: mapping.delimiter }
+    }
+}
+
+impl ImportFormat {
+    /// Guessed from the file extension, CSV unless it's `.json`.
+    pub fn from_path(path: &Path) -> ImportFormat {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("json") => ImportFormat::Json,
+            _ => ImportFormat::Csv,
+        }
+    }
+    pub fn importer(&self, mapping: &ColumnMapping) -> Box<dyn Importer> {
+        match self {
+            ImportFormat::Csv => Box::new(CsvImporter::new(mapping)),
+            ImportFormat::Json => Box::new(JsonImporter),
+        }
+    }
+}
+
+impl ImportReport {
+    pub fn summary(&self) -> String {
+        let mut summary = format!(
+            "Imported {} sessions, {} duplicates left out, {} rows skipped",
+            self.imported,
+            self.duplicates,
+            self.skipped.len()
+        );
+        for skipped in &self.skipped {
+            summary.push_str(&format!("\n  row {}: {}", skipped.row, skipped.reason));
+        }
+        summary
+    }
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            start: "start".to_string(),
+            end: Some("end".to_string()),
+            duration: Some("duration".to_string()),
+            duration_unit: DurationUnit::Seconds,
+            kind: Some("kind".to_string()),
+            subject: Some("subject".to_string()),
+            task: Some("task".to_string()),
+            completed: Some("completed".to_string()),
+            time_format: None,
+            delimiter: ',',
+        }
+    }
+}
+
+fn flatten(prefix: &str, value: Value, record: &mut Record) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                let key = match prefix {
+                    "" => key,
+                    prefix => format!("{prefix}.{key}"),
+                };
+                flatten(&key, value, record);
+            }
+        }
+        Value::Null => {}
+        Value::String(value) => {
+            record.insert(prefix.to_string(), value);
+        }
+        value => {
+            record.insert(prefix.to_string(), value.to_string());
+        }
+    }
+}
+
+/// Splits CSV into rows of fields, with quoted fields that may hold delimiters, quotes and newlines.
+fn parse_csv(data: &str, delimiter: char) -> Result<Vec<Vec<String>>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = data.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(StatsError::ImportError("a quoted field is never closed".to_string()).into());
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> SessionStore {
+        let path = std::env::temp_dir().join(format!("romodoro-import-{}-{}.json", name, std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        SessionStore::from_path(path).unwrap()
+    }
+
+    #[test]
+    fn csv_with_mapping() {
+        let data = "Start Time;Minutes;Type;Project\n\
+                    2025-03-03 09:00;25;Pomodoro;\"Thesis; chapter 2\"\n\
+                    2025-03-03 09:25;5;short break;\n\
+                    \n\
+                    yesterday;25;Pomodoro;\n\
+                    2025-03-03 10:00;25;nap;\n";
+        let mut mapping = ColumnMapping {
+            end: None,
+            duration_unit: DurationUnit::Minutes,
+            delimiter: ';',
+            ..ColumnMapping::default()
+        };
+        for pair in ["start=Start Time", "duration=Minutes", "kind=Type", "subject=Project"] {
+            mapping.set(pair).unwrap();
+        }
+        let mut store = temp_store("csv");
+        let report = import(&mut store, &CsvImporter::new(&mapping), &mapping, data).unwrap();
+        assert_eq!(report.imported, 2);
+        // the blank row still counts
+        assert_eq!(report.skipped.iter().map(|s| s.row).collect::<Vec<_>>(), [4, 5]);
+        assert!(report.skipped[0].reason.contains("can't read the time \"yesterday\""));
+        assert!(report.skipped[1].reason.contains("unknown kind \"nap\""));
+
+        let sessions = store.get_sessions();
+        assert_eq!(sessions[0].subject.as_deref(), Some("Thesis; chapter 2"));
+        assert_eq!((sessions[0].kind, sessions[0].duration), (SessionKind::Work, 1500));
+        assert_eq!(sessions[1].kind, SessionKind::Break);
+        assert_eq!(sessions[1].end - sessions[1].start, chrono::Duration::minutes(5));
+        assert!(mapping.set("project=Project").is_err());
+    }
+    #[test]
+    fn json_and_dedupe() {
+        let data = r#"{"count": 3, "entries": [
+            {"started": 1741000000, "stopped": 1741001500, "project": {"name": "rust"}},
+            {"started": 1741000600000, "stopped": 1741002100000, "project": {"name": "rust"}},
+            {"started": 1741010000, "project": {"name": "maths"}}
+        ]}"#;
+        let mapping = ColumnMapping {
+            start: "started".to_string(),
+            end: Some("stopped".to_string()),
+            subject: Some("project.name".to_string()),
+            ..ColumnMapping::default()
+        };
+        let mut store = temp_store("json");
+        let report = import(&mut store, &JsonImporter, &mapping, data).unwrap();
+        // the second entry overlaps the first one
+        assert_eq!((report.imported, report.duplicates), (1, 1));
+        assert_eq!(report.skipped, [SkippedRow { row: 3, reason: "no end or duration".to_string() }]);
+        assert_eq!(store.get_sessions()[0].subject.as_deref(), Some("rust"));
+
+        let again = import(&mut store, &JsonImporter, &mapping, data).unwrap();
+        assert_eq!((again.imported, again.duplicates), (0, 2));
+        assert!(import(&mut store, &JsonImporter, &mapping, "42").is_err());
+    }
+    #[test]
+    fn romodoro_exports_round_trip() {
+        let mut store = temp_store("round-trip");
+        let csv = "kind,start,end,duration,iteration,subject,task,completed,interruptions\n\
+                   long_break,2025-03-03T09:00:00+01:00,2025-03-03T09:15:00+01:00,900,1,,,true,0\n\
+                   work,2025-03-03T09:15:00+01:00,2025-03-03T09:25:00+01:00,600,2,rust,\"write, review\",false,1\n";
+        let mapping = ColumnMapping::default();
+        let report = import(&mut store, &CsvImporter::new(&mapping), &mapping, csv).unwrap();
+        assert_eq!(report, ImportReport { imported: 2, duplicates: 0, skipped: Vec::new() });
+        let work = &store.get_sessions()[1];
+        assert_eq!((work.task.as_deref(), work.completed, work.duration), (Some("write, review"), false, 600));
+        assert!(parse_csv("a,\"b\nc", ',').is_err());
+    }
+}
@@ -0,0 +1,479 @@
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::*;
+
+use crate::error::SettingsError;
+
+/// Where an action can be triggered.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyContext {
+    /// Works everywhere, so its keys can't be reused anywhere else.
+    Global,
+    Timer,
+    Settings,
+    /// The yes/no popups.
+    Confirm,
+    Stats,
+    Tasks,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    NextTab,
+    ToggleTimer,
+    Skip,
+    Extend,
+    Rewind,
+    RestartCycle,
+    NextSubject,
+    NextTask,
+    NextPreset,
+    InternalInterruption,
+    ExternalInterruption,
+    Up,
+    Down,
+    Increase,
+    Decrease,
+    Confirm,
+    RestoreDefaults,
+    AddSubject,
+    RenameSubject,
+    DeleteSubject,
+    Yes,
+    No,
+    ScrollBack,
+    ScrollForward,
+    ToggleRange,
+    ExportCsv,
+    ExportJson,
+    TaskUp,
+    TaskDown,
+    MoveTaskUp,
+    MoveTaskDown,
+    AddTask,
+    RenameTask,
+    DeleteTask,
+    CompleteTask,
+    ActivateTask,
+    IncreaseEstimate,
+    DecreaseEstimate,
+}
+
+/// A key with its modifiers, written like `q`, `Q`, `space`, `ctrl-c` or `shift-tab` in the config.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KeyChord {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+/// Action names mapped to key chords as written in the config, e.g. `quit = ["Q", "ctrl-c"]`.
+/// Actions left out keep their default keys.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct KeymapSettings(pub BTreeMap<String, Vec<String>>);
+
+/// Validated key bindings.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<KeyChord>)>,
+}
+
+impl KeyContext {
+    fn overlaps(&self, other: KeyContext) -> bool {
+        *self == other || *self == KeyContext::Global || other == KeyContext::Global
+    }
+}
+
+impl Action {
+    pub const ALL: [Action; 39] = [
+        Action::Quit,
+        Action::NextTab,
+        Action::ToggleTimer,
+        Action::Skip,
+        Action::Extend,
+        Action::Rewind,
+        Action::RestartCycle,
+        Action::NextSubject,
+        Action::NextTask,
+        Action::NextPreset,
+        Action::InternalInterruption,
+        Action::ExternalInterruption,
+        Action::Up,
+        Action::Down,
+        Action::Increase,
+        Action::Decrease,
+        Action::Confirm,
+        Action::RestoreDefaults,
+        Action::AddSubject,
+        Action::RenameSubject,
+        Action::DeleteSubject,
+        Action::Yes,
+        Action::No,
+        Action::ScrollBack,
+        Action::ScrollForward,
+        Action::ToggleRange,
+        Action::ExportCsv,
+        Action::ExportJson,
+        Action::TaskUp,
+        Action::TaskDown,
+        Action::MoveTaskUp,
+        Action::MoveTaskDown,
+        Action::AddTask,
+        Action::RenameTask,
+        Action::DeleteTask,
+        Action::CompleteTask,
+        Action::ActivateTask,
+        Action::IncreaseEstimate,
+        Action::DecreaseEstimate,
+    ];
+    /// Name used in the config.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::NextTab => "next_tab",
+            Action::ToggleTimer => "toggle_timer",
+            Action::Skip => "skip",
+            Action::Extend => "extend",
+            Action::Rewind => "rewind",
+            Action::RestartCycle => "restart_cycle",
+            Action::NextSubject => "next_subject",
+            Action::NextTask => "next_task",
+            Action::NextPreset => "next_preset",
+            Action::InternalInterruption => "internal_interruption",
+            Action::ExternalInterruption => "external_interruption",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Increase => "increase",
+            Action::Decrease => "decrease",
+            Action::Confirm => "confirm",
+            Action::RestoreDefaults => "restore_defaults",
+            Action::AddSubject => "add_subject",
+            Action::RenameSubject => "rename_subject",
+            Action::DeleteSubject => "delete_subject",
+            Action::Yes => "yes",
+            Action::No => "no",
+            Action::ScrollBack => "scroll_back",
+            Action::ScrollForward => "scroll_forward",
+            Action::ToggleRange => "toggle_range",
+            Action::ExportCsv => "export_csv",
+            Action::ExportJson => "export_json",
+            Action::TaskUp => "task_up",
+            Action::TaskDown => "task_down",
+            Action::MoveTaskUp => "move_task_up",
+            Action::MoveTaskDown => "move_task_down",
+            Action::AddTask => "add_task",
+            Action::RenameTask => "rename_task",
+            Action::DeleteTask => "delete_task",
+            Action::CompleteTask => "complete_task",
+            Action::ActivateTask => "activate_task",
+            Action::IncreaseEstimate => "increase_estimate",
+            Action::DecreaseEstimate => "decrease_estimate",
+        }
+    }
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+    pub fn context(&self) -> KeyContext {
+        match self {
+            Action::Quit | Action::NextTab => KeyContext::Global,
+            Action::ToggleTimer
+            | Action::Skip
+            | Action::Extend
+            | Action::Rewind
+            | Action::RestartCycle
+            | Action::NextSubject
+            | Action::NextTask
+            | Action::NextPreset
+            | Action::InternalInterruption
+            | Action::ExternalInterruption => KeyContext::Timer,
+            Action::Up
+            | Action::Down
+            | Action::Increase
+            | Action::Decrease
+            | Action::Confirm
+            | Action::RestoreDefaults
+            | Action::AddSubject
+            | Action::RenameSubject
+            | Action::DeleteSubject => KeyContext::Settings,
+            Action::Yes | Action::No => KeyContext::Confirm,
+            Action::ScrollBack
+            | Action::ScrollForward
+            | Action::ToggleRange
+            | Action::ExportCsv
+            | Action::ExportJson => KeyContext::Stats,
+            Action::TaskUp
+            | Action::TaskDown
+            | Action::MoveTaskUp
+            | Action::MoveTaskDown
+            | Action::AddTask
+            | Action::RenameTask
+            | Action::DeleteTask
+            | Action::CompleteTask
+            | Action::ActivateTask
+            | Action::IncreaseEstimate
+            | Action::DecreaseEstimate => KeyContext::Tasks,
+        }
+    }
+    /// Actions that can't be unbound, without them there's no way out of the app or its popups.
+    fn required(&self) -> bool {
+        matches!(self, Action::Quit | Action::NextTab | Action::Yes | Action::No)
+    }
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["Q"],
+            Action::NextTab => &["tab"],
+            Action::ToggleTimer | Action::Confirm | Action::ToggleRange | Action::CompleteTask => &["space"],
+            Action::Skip => &["n"],
+            Action::Extend => &["+"],
+            Action::Rewind => &["r"],
+            Action::RestartCycle => &["R"],
+            Action::NextSubject => &["s"],
+            Action::NextTask => &["t"],
+            Action::NextPreset => &["p"],
+            Action::InternalInterruption => &["i"],
+            Action::ExternalInterruption => &["x"],
+            Action::Up | Action::TaskUp => &["up"],
+            Action::Down | Action::TaskDown => &["down"],
+            Action::MoveTaskUp => &["K", "shift-up"],
+            Action::MoveTaskDown => &["J", "shift-down"],
+            Action::Increase | Action::ScrollForward | Action::IncreaseEstimate => &["right"],
+            Action::Decrease | Action::ScrollBack | Action::DecreaseEstimate => &["left"],
+            Action::ActivateTask => &["enter"],
+            Action::ExportCsv => &["c"],
+            Action::ExportJson => &["j"],
+            Action::RestoreDefaults => &["r"],
+            Action::AddSubject | Action::AddTask => &["a"],
+            Action::RenameSubject | Action::RenameTask => &["e"],
+            Action::DeleteSubject | Action::DeleteTask => &["d"],
+            Action::Yes => &["y"],
+            Action::No => &["n"],
+        }
+    }
+}
+
+impl Keymap {
+    /// The action `key` triggers in `context`.
+    pub fn action(&self, context: KeyContext, key: &KeyEvent) -> Option<Action> {
+        let chord = KeyChord::from(key);
+        self.bindings
+            .iter()
+            .find(|(action, keys)| action.context() == context && keys.contains(&chord))
+            .map(|(action, _)| *action)
+    }
+    pub fn keys(&self, action: Action) -> &[KeyChord] {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .map(|(_, keys)| keys.as_slice())
+            .unwrap_or_default()
+    }
+    /// First key of each action joined for the footer, e.g. `a/e/d`. `None` if none of them is bound.
+    pub fn hint(&self, actions: &[Action]) -> Option<String> {
+        let keys: Vec<String> = actions
+            .iter()
+            .filter_map(|action| self.keys(*action).first())
+            .map(|key| key.to_string())
+            .collect();
+        (!keys.is_empty()).then(|| keys.join("/"))
+    }
+}
+
+impl TryFrom<&KeymapSettings> for Keymap {
+    type Error = SettingsError;
+
+    fn try_from(settings: &KeymapSettings) -> Result<Self, Self::Error> {
+        if let Some(name) = settings.0.keys().find(|name| Action::from_name(name).is_none()) {
+            return Err(SettingsError::KeymapError(format!("unknown action \"{name}\"")));
+        }
+        let mut bindings = Vec::new();
+        for action in Action::ALL {
+            let keys: Vec<KeyChord> = match settings.0.get(action.name()) {
+                Some(keys) => keys
+                    .iter()
+                    .map(|key| key.parse())
+                    .collect::<Result<_, String>>()
+                    .map_err(|e| SettingsError::KeymapError(format!("{e} for {}", action.name())))?,
+                None => action
+                    .default_keys()
+                    .iter()
+                    .map(|key| key.parse().expect("Default keys should parse"))
+                    .collect(),
+            };
+            if keys.is_empty() && action.required() {
+                return Err(SettingsError::KeymapError(format!("{} needs at least one key", action.name())));
+            }
+            bindings.push((action, keys));
+        }
+        for (i, (action, keys)) in bindings.iter().enumerate() {
+            for (other, other_keys) in &bindings[i + 1..] {
+                if !action.context().overlaps(other.context()) {continue}
+                if let Some(key) = keys.iter().find(|key| other_keys.contains(key)) {
+                    return Err(SettingsError::KeymapError(format!(
+                        "{key} is bound to both {} and {}",
+                        action.name(),
+                        other.name()
+                    )));
+                }
+            }
+        }
+        Ok(Keymap { bindings })
+    }
+}
+
+impl FromStr for KeyChord {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        // `-` is both the separator and a key
+        let (prefix, key) = match input.strip_suffix("--") {
+            Some(prefix) => (prefix, "-"),
+            None => match input.rsplit_once('-') {
+                Some((prefix, key)) if !key.is_empty() => (prefix, key),
+                _ => ("", input),
+            },
+        };
+        let mut modifiers = KeyModifiers::NONE;
+        for modifier in prefix.split('-').filter(|m| !m.is_empty()) {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => KeyModifiers::CONTROL,
+                "alt" => KeyModifiers::ALT,
+                "shift" => KeyModifiers::SHIFT,
+                _ => return Err(format!("unknown modifier \"{modifier}\" in \"{input}\"")),
+            };
+        }
+        let mut chars = key.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => match key.to_lowercase().as_str() {
+                "space" => KeyCode::Char(' '),
+                "tab" => KeyCode::Tab,
+                "backtab" => KeyCode::BackTab,
+                "enter" => KeyCode::Enter,
+                "esc" => KeyCode::Esc,
+                "backspace" => KeyCode::Backspace,
+                "up" => KeyCode::Up,
+                "down" => KeyCode::Down,
+                "left" => KeyCode::Left,
+                "right" => KeyCode::Right,
+                "home" => KeyCode::Home,
+                "end" => KeyCode::End,
+                "pageup" => KeyCode::PageUp,
+                "pagedown" => KeyCode::PageDown,
+                "delete" => KeyCode::Delete,
+                "insert" => KeyCode::Insert,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(format!("unknown key \"{input}\"")),
+                },
+            },
+        };
+        Ok(KeyChord { code, modifiers }.normalized())
+    }
+}
+impl KeyChord {
+    // terminals report shifted letters and shift-tab with the shift modifier set, or not
+    fn normalized(mut self) -> Self {
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            match self.code {
+                KeyCode::Char(c) => self.code = KeyCode::Char(c.to_ascii_uppercase()),
+                KeyCode::Tab | KeyCode::BackTab => self.code = KeyCode::BackTab,
+                _ => return self,
+            }
+            self.modifiers.remove(KeyModifiers::SHIFT);
+        }
+        self
+    }
+}
+impl From<&KeyEvent> for KeyChord {
+    fn from(key: &KeyEvent) -> Self {
+        KeyChord { code: key.code, modifiers: key.modifiers }.normalized()
+    }
+}
+impl Display for KeyChord {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {write!(f, "Ctrl-")?}
+        if self.modifiers.contains(KeyModifiers::ALT) {write!(f, "Alt-")?}
+        if self.modifiers.contains(KeyModifiers::SHIFT) {write!(f, "Shift-")?}
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::BackTab => write!(f, "Shift-Tab"),
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+impl Default for KeymapSettings {
+    fn default() -> Self {
+        KeymapSettings(
+            Action::ALL
+                .iter()
+                .map(|action| (action.name().to_string(), action.default_keys().iter().map(|k| k.to_string()).collect()))
+                .collect(),
+        )
+    }
+}
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::try_from(&KeymapSettings::default()).expect("Default keymap should be valid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(bindings: &[(&str, &[&str])]) -> KeymapSettings {
+        KeymapSettings(
+            bindings
+                .iter()
+                .map(|(action, keys)| (action.to_string(), keys.iter().map(|k| k.to_string()).collect()))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn parse_chords() {
+        let chord = |code, modifiers| Ok(KeyChord { code, modifiers });
+        assert_eq!("q".parse(), chord(KeyCode::Char('q'), KeyModifiers::NONE));
+        assert_eq!("shift-q".parse(), chord(KeyCode::Char('Q'), KeyModifiers::NONE));
+        assert_eq!("ctrl-c".parse(), chord(KeyCode::Char('c'), KeyModifiers::CONTROL));
+        assert_eq!("shift-tab".parse(), chord(KeyCode::BackTab, KeyModifiers::NONE));
+        assert_eq!("ctrl--".parse(), chord(KeyCode::Char('-'), KeyModifiers::CONTROL));
+        assert_eq!("-".parse(), chord(KeyCode::Char('-'), KeyModifiers::NONE));
+        assert_eq!("F5".parse(), chord(KeyCode::F(5), KeyModifiers::NONE));
+        assert!("spcae".parse::<KeyChord>().is_err());
+        assert!("hyper-a".parse::<KeyChord>().is_err());
+        assert_eq!("ctrl-space".parse::<KeyChord>().unwrap().to_string(), "Ctrl-Space");
+    }
+    #[test]
+    fn overrides_and_lookup() {
+        let keymap = Keymap::try_from(&settings(&[("down", &["j", "down"]), ("up", &["k"])])).unwrap();
+        let shifted_q = KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT);
+        assert_eq!(keymap.action(KeyContext::Global, &shifted_q), Some(Action::Quit));
+        let j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::NONE);
+        assert_eq!(keymap.action(KeyContext::Settings, &j), Some(Action::Down));
+        assert_eq!(keymap.action(KeyContext::Timer, &j), None);
+        assert_eq!(keymap.hint(&[Action::Up, Action::Down]).as_deref(), Some("k/j"));
+        assert_eq!(Keymap::try_from(&settings(&[("skip", &[])])).unwrap().hint(&[Action::Skip]), None);
+    }
+    #[test]
+    fn invalid_keymaps() {
+        let error = |bindings| Keymap::try_from(&settings(bindings)).unwrap_err().to_string();
+        assert!(error(&[("quitt", &["q"])]).contains("unknown action \"quitt\""));
+        assert!(error(&[("quit", &["spcae"])]).contains("unknown key \"spcae\" for quit"));
+        assert!(error(&[("skip", &["s"])]).contains("s is bound to both skip and next_subject"));
+        assert!(error(&[("quit", &[])]).contains("quit needs at least one key"));
+        assert!(error(&[("no", &[])]).contains("no needs at least one key"));
+        // global keys clash with every tab
+        assert!(error(&[("quit", &["a"])]).contains("a is bound to both quit and add_subject"));
+        // but tabs don't clash with each other
+        assert!(Keymap::try_from(&settings(&[("skip", &["a"])])).is_ok());
+    }
+}
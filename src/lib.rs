@@ -1,12 +1,31 @@
 pub mod app;
+pub mod cli;
 pub mod timer;
 pub mod romodoro;
 pub mod settings;
 pub mod ui;
 pub mod stats;
+pub mod pixela;
 pub mod error;
+pub mod headless;
+pub mod notify;
+pub mod keymap;
+pub mod theme;
+pub mod font;
+pub mod tasks;
+pub mod export;
+pub mod import;
+pub mod control;
+pub mod hooks;
+pub mod profile;
 pub const DEFAULT_WORK: i64 = 1800;
 pub const DEFAULT_ITERATIONS: u8 = 4;
 pub const DEFAULT_BREAK: i64 = 300;
+pub const DEFAULT_LONG_BREAK: i64 = 900;
+pub const DEFAULT_LONG_BREAK_INTERVAL: u8 = 4;
 pub const WORK_TIME_INCR: i64 = 900;
 pub const BREAK_TIME_INCR: i64 = 60;
+pub const LONG_BREAK_TIME_INCR: i64 = 300;
+pub const EXTEND_TIME: i64 = 300;
+pub const PIXELA_URL: &str = "https://pixe.la";
+pub const PIXELA_GRAPH: &str = "romodoro";
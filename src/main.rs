@@ -1,28 +1,175 @@
 use std::cell::RefCell;
 use std::rc::Rc;
 
+use chrono::Local;
+use clap::Parser;
 use crossterm::terminal;
 use pomodoro::app::*;
+use pomodoro::cli::{self, Cli, Command, ConfigCommand};
+use pomodoro::headless::{self, OutputFormat, Outcome};
 use pomodoro::romodoro::*;
+use pomodoro::pixela::{self, PixelaClient, PixelaQueue};
 use pomodoro::settings::SettingsTab;
+use pomodoro::stats::SessionStore;
+use pomodoro::tasks::TaskList;
+use pomodoro::timer::TimerSnapshot;
 use pomodoro::error::Result;
+use pomodoro::control;
+use pomodoro::export::{self, ExportFilter};
+use pomodoro::import::{self, ColumnMapping, ImportFormat};
+use pomodoro::error::StatsError;
 // ALPHA 0.1
 
 
 #[tokio::main]
 async fn main() -> Result<()>{
+    let cli = Cli::parse();
+    let config_path = match &cli.config {
+        Some(path) => path.clone(),
+        None => SettingsTab::config_path()?,
+    };
+    let mut settings = SettingsTab::from_path(&config_path)?;
+    // overrides only last for this run
+    let saved_timer_settings = settings.timer_settings.clone();
+    let saved_preset = settings.preset.clone();
+    if let Some(name) = &cli.preset {
+        settings.apply_preset(name)?;
+    }
+    cli.apply_overrides(&mut settings.timer_settings);
+    let applied_timer_settings = settings.timer_settings.clone();
+
+    match &cli.command {
+        Some(Command::Stats { days }) => {
+            let stats = SessionStore::from_path(SessionStore::path_for(&config_path))?;
+            print!("{}", cli::stats_report(&stats, Local::now().date_naive(), *days));
+            return Ok(());
+        }
+        Some(Command::Export { format, from, to, subject, output }) => {
+            let stats = SessionStore::from_path(SessionStore::path_for(&config_path))?;
+            let filter = ExportFilter { from: *from, to: *to, subject: subject.clone() };
+            match output {
+                Some(path) => {
+                    let count = export::export_to_file(&stats, &filter, *format, path)?;
+                    eprintln!("Exported {} sessions to {}", count, path.display());
+                }
+                None => print!("{}", export::export(stats.get_sessions(), &filter, *format)?),
+            }
+            return Ok(());
+        }
+        Some(Command::Import { file, format, mapping, columns, duration_unit, dry_run }) => {
+            let mut column_mapping = match mapping {
+                Some(name) => settings
+                    .import_mappings
+                    .get(name)
+                    .cloned()
+                    .ok_or(StatsError::ImportError(format!("no mapping named \"{name}\" in the config")))?,
+                None => ColumnMapping::default(),
+            };
+            for pair in columns {
+                column_mapping.set(pair).map_err(StatsError::ImportError)?;
+            }
+            if let Some(unit) = duration_unit {
+                column_mapping.duration_unit = *unit;
+            }
+            let data = std::fs::read_to_string(file)?;
+            let format = format.unwrap_or_else(|| ImportFormat::from_path(file));
+            let mut stats = SessionStore::from_path(SessionStore::path_for(&config_path))?;
+            let report = import::import(&mut stats, format.importer(&column_mapping).as_ref(), &column_mapping, &data)?;
+            if !dry_run {
+                stats.save_to_file()?;
+            }
+            println!("{}", report.summary());
+            return Ok(());
+        }
+        Some(Command::Status { format, json }) => {
+            let Some(status) = control::running_status() else {
+                eprintln!("romodoro isn't running");
+                std::process::exit(1);
+            };
+            match json {
+                true => println!("{}", serde_json::to_string(&status).expect("Status should serialize")),
+                false => println!("{}", status.format(format)),
+            }
+            return Ok(());
+        }
+        Some(Command::Config { command: ConfigCommand::Show }) => {
+            print!("{}", toml::to_string(&settings).expect("Settings should be instantiated correctly"));
+            return Ok(());
+        }
+        Some(Command::Config { command: ConfigCommand::Path }) => {
+            println!("{}", config_path.display());
+            return Ok(());
+        }
+        Some(Command::Config { command: ConfigCommand::PixelaToken { token } }) => {
+            // keeps the token out of the shell history
+            let token = match token {
+                Some(token) => token.clone(),
+                None => {
+                    eprint!("Pixela token: ");
+                    let mut token = String::new();
+                    std::io::stdin().read_line(&mut token)?;
+                    token
+                }
+            };
+            let path = pixela::save_token(&config_path, &token)?;
+            eprintln!("Saved the Pixela token to {}", path.display());
+            return Ok(());
+        }
+        Some(Command::Start { .. }) | None => {}
+    }
+    let headless_format = match &cli.command {
+        Some(Command::Start { json: true, .. }) => Some(OutputFormat::Json),
+        Some(Command::Start { headless: true, .. }) => Some(OutputFormat::Text),
+        _ => None,
+    };
+    if headless_format.is_some() {
+        // nobody is around to unpause it
+        settings.ui_settings.pause_after_state_change = false;
+    }
+
     let (tx, rx) = tokio::sync::mpsc::channel(4);
     let (tx_events,  rx_events) = tokio::sync::mpsc::channel(32);
     let (tx_commands, rx_commands) = tokio::sync::mpsc::channel(4);
-    let settings =  Rc::new(RefCell::new(SettingsTab::new()?));
-    let pomodoro = Pomodoro::new(tx, rx_commands, tx_commands,settings.clone());
+    let settings =  Rc::new(RefCell::new(settings));
+    let stats = Rc::new(RefCell::new(SessionStore::from_path(SessionStore::path_for(&config_path))?));
+    let mut pomodoro = Pomodoro::new(tx, rx_commands, tx_commands,settings.clone(),stats.clone());
+    pomodoro.set_tasks(Rc::new(RefCell::new(TaskList::from_path(TaskList::path_for(&config_path))?)));
+    let pixela_client = PixelaClient::from_settings(&settings.borrow().stats_setting, &config_path);
+    if let Some(client) = pixela_client {
+        let (tx_pixela, rx_pixela) = tokio::sync::mpsc::channel(8);
+        let queue = PixelaQueue::from_path(PixelaQueue::path_for(&config_path))?;
+        tokio::spawn(pixela::run_worker(client, queue, rx_pixela));
+        pomodoro.set_pixela_sender(tx_pixela);
+    }
+    if let Some(format) = headless_format {
+        let mut rx = rx;
+        let outcome = headless::run(&mut pomodoro, &mut rx, format, &mut std::io::stdout()).await?;
+        stats.borrow().save_to_file()?;
+        if outcome == Outcome::Interrupted {
+            std::process::exit(130);
+        }
+        return Ok(());
+    }
+    // headless runs leave the snapshot alone, it belongs to the session waiting to be resumed
+    let snapshot_path = TimerSnapshot::path_for(&config_path);
+    let snapshot = TimerSnapshot::load(&snapshot_path);
+    pomodoro.set_snapshot_path(snapshot_path);
     terminal::enable_raw_mode()?;
     let mut terminal = ratatui::init();
     let mut app = App::new(pomodoro,settings);
+    if let Some(snapshot) = snapshot {
+        app.offer_resume(snapshot);
+    }
     let app_result = app.run(&mut terminal,rx_events,tx_events,rx).await; // mainloop
     terminal::disable_raw_mode()?;
 
     ratatui::restore();
-    app.get_settings_ref().borrow().save_to_file()?;
+    app.get_pomodoro_ref().save_snapshot();
+    if !cli.no_save {
+        let mut settings = app.get_settings_ref().borrow().clone();
+        cli.restore_overrides(&mut settings, &saved_timer_settings, saved_preset, &applied_timer_settings);
+        settings.save_to_path(&config_path)?;
+    }
+    stats.borrow().save_to_file()?;
     Ok(app_result?)
 }
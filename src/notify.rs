@@ -0,0 +1,239 @@
+use std::{fmt::Debug, io::{self, Write}, process::Stdio};
+
+use serde::*;
+
+use crate::romodoro::Transition;
+use crate::timer::{PomodoroState, Timer};
+
+/// Every notification is off until it's turned on in the config.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Ring the terminal bell.
+    pub bell: bool,
+    /// OSC 9 notification, understood by iTerm2, Windows Terminal, kitty and others.
+    pub osc9: bool,
+    /// OSC 777 notification, understood by urxvt, foot and VTE based terminals.
+    pub osc777: bool,
+    /// Shell command run with `ROMODORO_EVENT`, `ROMODORO_TITLE` and `ROMODORO_BODY` set.
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NotificationKind {
+    WorkEnded,
+    BreakEnded,
+    CycleComplete,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub kind: NotificationKind,
+    pub title: String,
+    pub body: String,
+}
+
+pub trait NotificationBackend: Debug {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()>;
+}
+
+#[derive(Debug)]
+pub struct Bell<W: Write + Debug>(pub W);
+
+#[derive(Debug)]
+pub struct Osc9<W: Write + Debug>(pub W);
+
+#[derive(Debug)]
+pub struct Osc777<W: Write + Debug>(pub W);
+
+#[derive(Debug)]
+pub struct ShellCommand(pub String);
+
+/// Sends every notification to all configured backends.
+#[derive(Debug, Default)]
+pub struct Notifier {
+    backends: Vec<Box<dyn NotificationBackend>>,
+}
+
+impl NotificationKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NotificationKind::WorkEnded => "work_end",
+            NotificationKind::BreakEnded => "break_end",
+            NotificationKind::CycleComplete => "cycle_complete",
+        }
+    }
+}
+
+impl Notification {
+    pub fn from_transition(transition: &Transition, timer: &Timer) -> Self {
+        let progress = format!("{}/{}", timer.get_iteration(), timer.get_total_iterations());
+        // a profile's phases go by their labels, the timer is already on the phase that started
+        let phases = timer.get_phases();
+        let ended_phase = timer.get_phase_at(timer.get_iteration().saturating_sub(1));
+        match transition {
+            Transition::CycleComplete { .. } if !phases.is_empty() => Notification {
+                kind: NotificationKind::CycleComplete,
+                title: "Cycle complete".to_string(),
+                body: format!("All {} phases done", phases.len()),
+            },
+            Transition::Next { ended, started } if ended_phase.is_some() => Notification {
+                kind: match ended {
+                    PomodoroState::Work(_) => NotificationKind::WorkEnded,
+                    PomodoroState::Break(_) | PomodoroState::LongBreak(_) => NotificationKind::BreakEnded,
+                },
+                title: format!("{} finished", ended_phase.map(|phase| phase.label.as_str()).unwrap_or_default()),
+                body: format!("Next: {} ({} min), {}", timer.get_state_label(), Timer::get_duration(started) / 60, progress),
+            },
+            Transition::CycleComplete { .. } => Notification {
+                kind: NotificationKind::CycleComplete,
+                title: "Cycle complete".to_string(),
+                body: format!("All {} iterations done", timer.get_total_iterations()),
+            },
+            Transition::Next { ended: PomodoroState::Work(_), started } => Notification {
+                kind: NotificationKind::WorkEnded,
+                title: "Work finished".to_string(),
+                body: format!("Time for a {} ({} min), {}", started.to_string().to_lowercase(), Timer::get_duration(started) / 60, progress),
+            },
+            Transition::Next { ended, started } => Notification {
+                kind: NotificationKind::BreakEnded,
+                title: format!("{} is over", ended),
+                body: format!("Back to work ({} min), {}", Timer::get_duration(started) / 60, progress),
+            },
+        }
+    }
+}
+
+// Control characters would end the escape sequence early.
+fn sanitize(text: &str) -> String {
+    text.chars().filter(|c| !c.is_control() && *c != ';').collect()
+}
+
+impl<W: Write + Debug> NotificationBackend for Bell<W> {
+    fn notify(&mut self, _: &Notification) -> io::Result<()> {
+        self.0.write_all(b"\x07")?;
+        self.0.flush()
+    }
+}
+impl<W: Write + Debug> NotificationBackend for Osc9<W> {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        write!(self.0, "\x1b]9;{}: {}\x07", sanitize(&notification.title), sanitize(&notification.body))?;
+        self.0.flush()
+    }
+}
+impl<W: Write + Debug> NotificationBackend for Osc777<W> {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        write!(self.0, "\x1b]777;notify;{};{}\x07", sanitize(&notification.title), sanitize(&notification.body))?;
+        self.0.flush()
+    }
+}
+impl NotificationBackend for ShellCommand {
+    fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+        // not awaited, tokio reaps the child in the background
+        tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(&self.0)
+            .env("ROMODORO_EVENT", notification.kind.name())
+            .env("ROMODORO_TITLE", &notification.title)
+            .env("ROMODORO_BODY", &notification.body)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        Ok(())
+    }
+}
+
+impl Notifier {
+    /// Terminal backends write to stderr so they don't mix with headless output on stdout.
+    pub fn from_settings(settings: &NotificationSettings) -> Self {
+        let mut notifier = Notifier::default();
+        if settings.bell {notifier.add_backend(Box::new(Bell(io::stderr())))}
+        if settings.osc9 {notifier.add_backend(Box::new(Osc9(io::stderr())))}
+        if settings.osc777 {notifier.add_backend(Box::new(Osc777(io::stderr())))}
+        if let Some(command) = &settings.command {
+            notifier.add_backend(Box::new(ShellCommand(command.clone())));
+        }
+        notifier
+    }
+    pub fn add_backend(&mut self, backend: Box<dyn NotificationBackend>) {
+        self.backends.push(backend);
+    }
+    /// A failing backend doesn't stop the others.
+    pub fn notify(&mut self, notification: &Notification) {
+        for backend in self.backends.iter_mut() {
+            let _ = backend.notify(notification);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::profile::{Phase, PhaseKind};
+
+    /// Backend that keeps what it was sent.
+    #[derive(Debug, Default, Clone)]
+    pub struct FakeBackend(pub Rc<RefCell<Vec<Notification>>>);
+
+    impl NotificationBackend for FakeBackend {
+        fn notify(&mut self, notification: &Notification) -> io::Result<()> {
+            self.0.borrow_mut().push(notification.clone());
+            Ok(())
+        }
+    }
+
+    fn notification() -> Notification {
+        Notification { kind: NotificationKind::WorkEnded, title: "Work finished".to_string(), body: "Time for a break;\x07".to_string() }
+    }
+
+    #[test]
+    fn escape_sequences() {
+        let mut bell = Bell(Vec::new());
+        let mut osc9 = Osc9(Vec::new());
+        let mut osc777 = Osc777(Vec::new());
+        bell.notify(&notification()).unwrap();
+        osc9.notify(&notification()).unwrap();
+        osc777.notify(&notification()).unwrap();
+        assert_eq!(bell.0, b"\x07");
+        assert_eq!(osc9.0, b"\x1b]9;Work finished: Time for a break\x07");
+        assert_eq!(osc777.0, b"\x1b]777;notify;Work finished;Time for a break\x07");
+    }
+    #[test]
+    fn from_transition() {
+        let timer = Timer::default();
+        let work = timer.get_work_state();
+        let pause = timer.get_break_state();
+        let kinds: Vec<NotificationKind> = [
+            Transition::Next { ended: work, started: pause },
+            Transition::Next { ended: pause, started: work },
+            Transition::CycleComplete { ended: work },
+        ]
+        .iter()
+        .map(|t| Notification::from_transition(t, &timer).kind)
+        .collect();
+        assert_eq!(kinds, [NotificationKind::WorkEnded, NotificationKind::BreakEnded, NotificationKind::CycleComplete]);
+    }
+    #[tokio::test]
+    async fn phases_go_by_label() {
+        let mut timer = Timer::default();
+        timer.set_phases(vec![Phase::new("Warm-up", 300, PhaseKind::Work), Phase::new("Stretch", 600, PhaseKind::Break)]);
+        timer.next_iteration().await;
+        let transition = Transition::Next { ended: PomodoroState::Work(300), started: timer.get_current_state() };
+        let notification = Notification::from_transition(&transition, &timer);
+        assert_eq!(notification.kind, NotificationKind::WorkEnded);
+        assert_eq!(notification.title, "Warm-up finished");
+        assert_eq!(notification.body, "Next: Stretch (10 min), 2/2");
+    }
+    #[test]
+    fn notifier_fans_out() {
+        let fake = FakeBackend::default();
+        let mut notifier = Notifier::default();
+        notifier.add_backend(Box::new(fake.clone()));
+        notifier.add_backend(Box::new(fake.clone()));
+        notifier.notify(&notification());
+        assert_eq!(fake.0.borrow().len(), 2);
+    }
+}
@@ -0,0 +1,355 @@
+use std::{fs, io::{self, Write}, path::{Path, PathBuf}, time::Duration};
+
+use chrono::NaiveDate;
+use serde::*;
+use tokio::sync::mpsc::Receiver;
+
+use crate::error::{Result, SettingsError, StatsError};
+use crate::settings::{SettingsTab, StatsSettings};
+
+const RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// Total focused minutes of a single day, waiting to be pushed to Pixela.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PixelaEntry {
+    /// Pixela date format, `yyyyMMdd`.
+    pub date: String,
+    pub quantity: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PixelaClient {
+    base_url: String,
+    username: String,
+    token: String,
+    graph_id: String,
+    http: reqwest::Client,
+}
+
+/// Entries that still have to be sent, persisted so a failed POST survives a restart.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct PixelaQueue {
+    /// `username/graph_id` of the graph created last, a different one is created on the next flush.
+    created_graph: Option<String>,
+    entries: Vec<PixelaEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+fn config_dir(config_path: &Path) -> &Path {
+    config_path.parent().unwrap_or(Path::new("."))
+}
+
+/// Reads the Pixela token from `PIXELA_TOKEN` or from the `pixela_token` file next to the config at `config_path`.
+pub fn load_token(config_path: &Path) -> Option<String> {
+    if let Ok(token) = std::env::var("PIXELA_TOKEN") {
+        return Some(token);
+    }
+    read_token(config_dir(config_path))
+}
+fn read_token(dir: &Path) -> Option<String> {
+    let token = fs::read_to_string(dir.join("pixela_token")).ok()?;
+    let token = token.trim();
+    (!token.is_empty()).then(|| token.to_string())
+}
+
+/// Stores the token for `romodoro config pixela-token`, returns where it went.
+pub fn save_token(config_path: &Path, token: &str) -> Result<PathBuf> {
+    write_token(config_dir(config_path), token)
+}
+fn write_token(dir: &Path, token: &str) -> Result<PathBuf> {
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(SettingsError::SaveError("the Pixela token is empty".to_string()).into());
+    }
+    if !dir.exists() {fs::create_dir_all(dir)?}
+    let path = dir.join("pixela_token");
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(&path)?;
+    // the mode only applies to new files, an older one is locked down before the token goes in
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(token.as_bytes())?;
+    Ok(path)
+}
+
+impl PixelaEntry {
+    pub fn new(date: NaiveDate, minutes: i64) -> Self {
+        PixelaEntry { date: date.format("%Y%m%d").to_string(), quantity: minutes }
+    }
+}
+
+impl PixelaClient {
+    pub fn new(base_url: &str, username: &str, token: &str, graph_id: &str) -> Self {
+        PixelaClient {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            username: username.to_string(),
+            token: token.to_string(),
+            graph_id: graph_id.to_string(),
+            http: reqwest::Client::new(),
+        }
+    }
+    /// Returns `None` when stats are off or the username/token is missing.
+    pub fn from_settings(settings: &StatsSettings, config_path: &Path) -> Option<Self> {
+        if !settings.stats_on {return None}
+        let username = settings.pixela_username.as_ref()?;
+        let token = load_token(config_path)?;
+        Some(PixelaClient::new(&settings.pixela_base_url, username, &token, &settings.pixela_graph))
+    }
+    pub async fn create_graph(&self) -> Result<()> {
+        let body = serde_json::json!({
+            "id": self.graph_id,
+            "name": "romodoro",
+            "unit": "minutes",
+            "type": "int",
+            "color": "sora",
+        });
+        let response = self.http
+            .post(format!("{}/v1/users/{}/graphs", self.base_url, self.username))
+            .header("X-USER-TOKEN", &self.token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| StatsError::PixelaError(e.to_string()))?;
+        // 409 means the graph is already there
+        match response.status() {
+            status if status.is_success() || status == reqwest::StatusCode::CONFLICT => Ok(()),
+            status => Err(StatsError::PixelaError(format!("creating graph returned {status}")).into()),
+        }
+    }
+    pub async fn post_pixel(&self, entry: &PixelaEntry) -> Result<()> {
+        let body = serde_json::json!({
+            "date": entry.date,
+            "quantity": entry.quantity.to_string(),
+        });
+        let response = self.http
+            .post(format!("{}/v1/users/{}/graphs/{}", self.base_url, self.username, self.graph_id))
+            .header("X-USER-TOKEN", &self.token)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| StatsError::PixelaError(e.to_string()))?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(StatsError::PixelaError(format!("posting pixel returned {status}")).into()),
+        }
+    }
+}
+
+impl PixelaQueue {
+    pub fn new() -> Result<PixelaQueue> {
+        PixelaQueue::from_path(PixelaQueue::path_for(&SettingsTab::config_path()?))
+    }
+    /// Where the queue goes for the config at `config_path`.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_file_name("pixela_queue.json")
+    }
+    pub fn from_path(path: impl AsRef<Path>) -> Result<PixelaQueue> {
+        let path = path.as_ref().to_path_buf();
+        let mut queue: PixelaQueue = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| StatsError::LoadError(e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => PixelaQueue::default(),
+            Err(e) => return Err(e.into()),
+        };
+        queue.path = path;
+        Ok(queue)
+    }
+    pub fn save_to_file(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            if !dir.exists() {fs::create_dir_all(dir)?}
+        }
+        let json = serde_json::to_string_pretty(&self).map_err(|e| StatsError::SaveError(e.to_string()))?;
+        fs::write(&self.path, json)?;
+        Ok(())
+    }
+    /// Queues an entry. Pixels hold a daily total, so a newer entry replaces an older one for the same day.
+    pub fn push(&mut self, entry: PixelaEntry) {
+        self.entries.retain(|e| e.date != entry.date);
+        self.entries.push(entry);
+    }
+    pub fn get_entries(&self) -> &[PixelaEntry] {
+        &self.entries
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    /// Creates the graph if needed and sends queued entries in order, stopping at the first failure.
+    pub async fn flush(&mut self, client: &PixelaClient) -> Result<usize> {
+        let graph = format!("{}/{}", client.username, client.graph_id);
+        if self.created_graph.as_ref() != Some(&graph) {
+            client.create_graph().await?;
+            self.created_graph = Some(graph);
+            self.save_to_file()?;
+        }
+        let mut sent = 0;
+        while let Some(entry) = self.entries.first() {
+            client.post_pixel(entry).await?;
+            self.entries.remove(0);
+            self.save_to_file()?;
+            sent += 1;
+        }
+        Ok(sent)
+    }
+}
+
+/// Background task that pushes entries as they come and retries whatever is left in the queue.
+pub async fn run_worker(client: PixelaClient, mut queue: PixelaQueue, mut entry_rx: Receiver<PixelaEntry>) {
+    let mut retry = tokio::time::interval(RETRY_INTERVAL);
+    loop {
+        tokio::select! {
+            entry = entry_rx.recv() => {
+                match entry {
+                    Some(entry) => {
+                        queue.push(entry);
+                        let _ = queue.save_to_file();
+                        let _ = queue.flush(&client).await;
+                    },
+                    None => {break},
+                }
+            }
+            _ = retry.tick(), if !queue.is_empty() => {
+                let _ = queue.flush(&client).await;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::{matchers::{body_json, header, method, path}, Mock, MockServer, ResponseTemplate};
+
+    fn temp_queue(name: &str) -> PixelaQueue {
+        let path = std::env::temp_dir().join(format!("romodoro-pixela-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        PixelaQueue::from_path(path).unwrap()
+    }
+
+    #[test]
+    fn token_round_trip() {
+        let dir = std::env::temp_dir().join(format!("romodoro-token-{}", std::process::id()));
+        assert_eq!(read_token(&dir), None);
+        assert!(write_token(&dir, "  ").is_err());
+        let path = write_token(&dir, " secret\n").unwrap();
+        assert_eq!(read_token(&dir).as_deref(), Some("secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+            write_token(&dir, "other").unwrap();
+            assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o600);
+            assert_eq!(read_token(&dir).as_deref(), Some("other"));
+        }
+        let _ = fs::remove_dir_all(&dir);
+    }
+    #[test]
+    fn push_replaces_same_day() {
+        let mut queue = temp_queue("push");
+        let day = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        queue.push(PixelaEntry::new(day, 25));
+        queue.push(PixelaEntry::new(day.succ_opt().unwrap(), 25));
+        queue.push(PixelaEntry::new(day, 50));
+        assert_eq!(queue.get_entries(), &[
+            PixelaEntry { date: "20250311".to_string(), quantity: 25 },
+            PixelaEntry { date: "20250310".to_string(), quantity: 50 },
+        ]);
+        assert!(PixelaQueue::from_path(std::env::temp_dir()).is_err());
+    }
+
+    #[tokio::test]
+    async fn flush_creates_graph_and_posts() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/users/tester/graphs"))
+            .and(header("X-USER-TOKEN", "secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/users/tester/graphs/romodoro"))
+            .and(header("X-USER-TOKEN", "secret"))
+            .and(body_json(serde_json::json!({"date": "20250310", "quantity": "25"})))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let client = PixelaClient::new(&server.uri(), "tester", "secret", "romodoro");
+        let mut queue = temp_queue("flush");
+        queue.push(PixelaEntry::new(NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(), 25));
+        assert_eq!(queue.flush(&client).await.unwrap(), 1);
+        assert!(queue.is_empty());
+
+        let saved = PixelaQueue::from_path(&queue.path).unwrap();
+        assert_eq!(saved.created_graph.as_deref(), Some("tester/romodoro"));
+        assert!(saved.is_empty());
+        let _ = fs::remove_file(&queue.path);
+    }
+
+    #[tokio::test]
+    async fn changed_graph_is_created() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/users/tester/graphs"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&server)
+            .await;
+        for graph in ["romodoro", "focus"] {
+            Mock::given(method("POST"))
+                .and(path(format!("/v1/users/tester/graphs/{graph}")))
+                .respond_with(ResponseTemplate::new(200))
+                .mount(&server)
+                .await;
+        }
+
+        let day = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut queue = temp_queue("graph");
+        queue.push(PixelaEntry::new(day, 25));
+        queue.flush(&PixelaClient::new(&server.uri(), "tester", "secret", "romodoro")).await.unwrap();
+        // pixela_graph was changed in the config
+        let client = PixelaClient::new(&server.uri(), "tester", "secret", "focus");
+        for _ in 0..2 {
+            queue.push(PixelaEntry::new(day, 50));
+            assert_eq!(queue.flush(&client).await.unwrap(), 1);
+        }
+        assert_eq!(queue.created_graph.as_deref(), Some("tester/focus"));
+        let _ = fs::remove_file(&queue.path);
+    }
+
+    #[tokio::test]
+    async fn failed_post_stays_queued() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/v1/users/tester/graphs"))
+            .respond_with(ResponseTemplate::new(409))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/v1/users/tester/graphs/romodoro"))
+            .respond_with(ResponseTemplate::new(503))
+            .mount(&server)
+            .await;
+
+        let client = PixelaClient::new(&server.uri(), "tester", "secret", "romodoro");
+        let mut queue = temp_queue("retry");
+        queue.push(PixelaEntry::new(NaiveDate::from_ymd_opt(2025, 3, 10).unwrap(), 25));
+        queue.save_to_file().unwrap();
+        assert!(queue.flush(&client).await.is_err());
+
+        let saved = PixelaQueue::from_path(&queue.path).unwrap();
+        assert_eq!(saved.get_entries().len(), 1);
+        let _ = fs::remove_file(&queue.path);
+    }
+}
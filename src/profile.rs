@@ -0,0 +1,116 @@
+use std::{collections::BTreeMap, str::FromStr};
+
+use ratatui::style::Color;
+use serde::*;
+
+use crate::error::SettingsError;
+use crate::timer::PomodoroState;
+
+/// Whether a phase counts as focus time, and which theme colour it falls back to.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PhaseKind {
+    #[default]
+    Work,
+    Break,
+    LongBreak,
+}
+
+/// One step of a profile, e.g. `{ label = "Warm-up", duration = 300, colour = "yellow" }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Phase {
+    pub label: String,
+    /// Seconds.
+    pub duration: i64,
+    #[serde(default)]
+    pub kind: PhaseKind,
+    /// Name like `lightblue`, `#rrggbb` or a 0-255 index, the theme's colour for `kind` if unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub colour: Option<String>,
+}
+
+/// Phases the timer steps through in order instead of alternating work and breaks.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub phases: Vec<Phase>,
+}
+
+impl Phase {
+    pub fn new(label: &str, duration: i64, kind: PhaseKind) -> Phase {
+        Phase { label: label.to_string(), duration, kind, colour: None }
+    }
+    pub fn state(&self) -> PomodoroState {
+        match self.kind {
+            PhaseKind::Work => PomodoroState::Work(self.duration),
+            PhaseKind::Break => PomodoroState::Break(self.duration),
+            PhaseKind::LongBreak => PomodoroState::LongBreak(self.duration),
+        }
+    }
+    /// `None` without a colour of its own.
+    pub fn get_colour(&self) -> Option<Color> {
+        Color::from_str(self.colour.as_deref()?).ok()
+    }
+}
+
+impl Profile {
+    /// Checks every profile can be run, so a typo is reported on load instead of at the phase.
+    pub fn validate_all(profiles: &BTreeMap<String, Profile>) -> Result<(), SettingsError> {
+        for (name, profile) in profiles {
+            if profile.phases.is_empty() {
+                return Err(SettingsError::ProfileError(format!("profile \"{name}\" has no phases")));
+            }
+            if profile.phases.len() > u8::MAX as usize {
+                return Err(SettingsError::ProfileError(format!("profile \"{name}\" has more than {} phases", u8::MAX)));
+            }
+            for phase in &profile.phases {
+                if phase.duration <= 0 {
+                    return Err(SettingsError::ProfileError(format!(
+                        "phase \"{}\" in profile \"{name}\" needs a positive duration",
+                        phase.label
+                    )));
+                }
+                if phase.colour.is_some() && phase.get_colour().is_none() {
+                    return Err(SettingsError::ProfileError(format!(
+                        "invalid colour \"{}\" for phase \"{}\" in profile \"{name}\"",
+                        phase.colour.as_deref().unwrap_or_default(),
+                        phase.label
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_validate() {
+        let profiles: BTreeMap<String, Profile> = toml::from_str(
+            r##"
+            [deep]
+            phases = [
+                { label = "Warm-up", duration = 300, colour = "yellow" },
+                { label = "Deep work", duration = 3000 },
+                { label = "Break", duration = 600, kind = "break" },
+                { label = "Review", duration = 600, colour = "#458588" },
+            ]
+            "##,
+        )
+        .unwrap();
+        let phases = &profiles["deep"].phases;
+        assert_eq!(phases[1].state(), PomodoroState::Work(3000));
+        assert_eq!(phases[2].state(), PomodoroState::Break(600));
+        assert_eq!(phases[0].get_colour(), Some(Color::Yellow));
+        assert_eq!(phases[1].get_colour(), None);
+        assert!(Profile::validate_all(&profiles).is_ok());
+
+        let mut broken = profiles.clone();
+        broken.get_mut("deep").unwrap().phases[3].colour = Some("blurple".to_string());
+        assert!(Profile::validate_all(&broken).unwrap_err().to_string().contains("\"blurple\" for phase \"Review\""));
+        let empty = BTreeMap::from([("empty".to_string(), Profile::default())]);
+        assert!(Profile::validate_all(&empty).unwrap_err().to_string().contains("\"empty\" has no phases"));
+    }
+}
@@ -1,22 +1,46 @@
-use std::{cell::RefCell, rc::Rc};
+use std::{cell::RefCell, cmp::Ordering, path::PathBuf, rc::Rc};
 
+use chrono::Local;
 use tokio_util::sync::CancellationToken;
 
-use crate::{app::Event, settings::{self, PomodoroSettings, SettingsTab}, timer::{self, *}};
+use crate::{app::Event, profile::Phase, hooks::{Hook, HookEvent, HookFailure}, notify::{Notification, Notifier}, pixela::PixelaEntry, settings::{PomodoroSettings, SettingsTab}, stats::{InterruptionKind, Session, SessionKind, SessionStore}, tasks::TaskList, timer::*};
+
+/// Seconds between timer snapshots while the countdown runs.
+const SNAPSHOT_INTERVAL: i64 = 15;
+
+/// What happened when an interval ran out.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    /// The timer moved on from `ended` to `started`.
+    Next { ended: PomodoroState, started: PomodoroState },
+    /// The last interval of the cycle ended.
+    CycleComplete { ended: PomodoroState },
+}
 
 #[derive(Debug)]
 pub struct Pomodoro {
     pub timer : Timer,
     time_sender: tokio::sync::mpsc::Sender<i64>,
     command_rx: Option<tokio::sync::mpsc::Receiver<TimerCommand>>,
-    settings: Rc<RefCell<SettingsTab>>
+    settings: Rc<RefCell<SettingsTab>>,
+    stats: Rc<RefCell<SessionStore>>,
+    tasks: Rc<RefCell<TaskList>>,
+    pixela_tx: Option<tokio::sync::mpsc::Sender<PixelaEntry>>,
+    notifier: Notifier,
+    /// Where hook failures are reported, stderr if unset.
+    hook_tx: Option<tokio::sync::mpsc::Sender<Event>>,
+    /// Last hook that failed, shown until the timer is started or paused again.
+    hook_failure: Option<HookFailure>,
+    snapshot_path: Option<PathBuf>,
 }
 impl Pomodoro {
-    pub fn new(time_sender: tokio::sync::mpsc::Sender<i64>, command_rx: tokio::sync::mpsc::Receiver<TimerCommand>,command_tx: tokio::sync::mpsc::Sender<TimerCommand>, settings: Rc<RefCell<SettingsTab>>) -> Self {
+    pub fn new(time_sender: tokio::sync::mpsc::Sender<i64>, command_rx: tokio::sync::mpsc::Receiver<TimerCommand>,command_tx: tokio::sync::mpsc::Sender<TimerCommand>, settings: Rc<RefCell<SettingsTab>>, stats: Rc<RefCell<SessionStore>>) -> Self {
         let mut timer = Timer::from(settings.borrow().timer_settings.clone());
         timer.set_config(settings.clone());
+        timer.set_phases(settings.borrow().get_phases());
         timer.countdown_command_tx = Some(command_tx);
-        Pomodoro {timer, command_rx: Some(command_rx), time_sender, settings}
+        let notifier = Notifier::from_settings(&settings.borrow().ui_settings.notifications);
+        Pomodoro {timer, command_rx: Some(command_rx), time_sender, settings, stats, tasks: Rc::default(), pixela_tx: None, notifier, hook_tx: None, hook_failure: None, snapshot_path: None}
     }
     pub async fn create_countdown(&mut self, cancel_token: CancellationToken) { 
         let sender = self.time_sender.clone();
@@ -25,12 +49,78 @@ impl Pomodoro {
         self.timer.run(sender, command_rx, cancel_token).await;
     }
     pub async fn cycle(&mut self) {
+        let state = self.timer.get_current_state();
+        self.hook_failure = None;
         if self.timer.get_running() {
             self.timer.stop().await;
+            self.run_hooks(&[(HookEvent::Pause, state, self.timer.get_iteration())]);
         }
         else {
+            // starting a finished cycle starts a new one
+            if self.timer.is_completed() {
+                self.timer.restart().await;
+            }
             self.timer.start().await;
+            self.run_hooks(&[(HookEvent::start_of(state), state, self.timer.get_iteration())]);
+        }
+        self.save_snapshot();
+    }
+    /// Ends the current interval early. Skipped intervals that ran are recorded as not completed.
+    pub async fn skip(&mut self) -> Option<Transition> {
+        if self.timer.is_completed() {return None}
+        if self.timer.get_interval_start().is_some() {
+            self.record_session(false).await;
+        }
+        let transition = self.finish_interval().await;
+        if let Some(transition) = &transition {
+            self.run_transition_hooks(transition);
         }
+        self.save_snapshot();
+        transition
+    }
+    pub async fn extend(&mut self, seconds: i64) {
+        self.timer.extend(seconds).await;
+        self.set_time_left(self.timer.get_timeleft());
+        self.save_snapshot();
+    }
+    pub async fn rewind(&mut self) {
+        self.timer.rewind().await;
+        self.set_time_left(self.timer.get_timeleft());
+        self.save_snapshot();
+    }
+    /// Goes back to the first work interval, keeping the subject.
+    pub async fn restart_cycle(&mut self) {
+        self.timer.restart().await;
+        self.save_snapshot();
+    }
+    /// Logs an interruption of the running work interval, `false` if there is none.
+    pub fn interrupt(&mut self, kind: InterruptionKind) -> bool {
+        let logged = self.timer.interrupt(kind, Local::now());
+        if logged {
+            self.save_snapshot();
+        }
+        logged
+    }
+    pub fn note_interruption(&mut self, note: &str) {
+        self.timer.note_interruption(note);
+        self.save_snapshot();
+    }
+    pub fn set_snapshot_path(&mut self, path: PathBuf) {
+        self.snapshot_path = Some(path);
+    }
+    /// Keeps the snapshot file in line with the timer, removing it once there is nothing to resume.
+    pub fn save_snapshot(&self) {
+        let Some(path) = &self.snapshot_path else {return};
+        if self.timer.in_progress() {
+            let _ = self.timer.snapshot(Local::now()).save(path);
+        } else {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+    pub async fn restore(&mut self, snapshot: &TimerSnapshot) {
+        self.timer.restore(snapshot, Local::now()).await;
+        self.set_time_left(self.timer.get_timeleft());
+        self.save_snapshot();
     }
     pub fn get_work_state(&self) -> PomodoroState {
          self.timer.get_work_state()
@@ -38,6 +128,63 @@ impl Pomodoro {
     pub fn get_setting_ref(&self) -> Rc<RefCell<SettingsTab>> {
         self.settings.clone()
     }
+    pub fn get_stats_ref(&self) -> Rc<RefCell<SessionStore>> {
+        self.stats.clone()
+    }
+    pub fn get_tasks_ref(&self) -> Rc<RefCell<TaskList>> {
+        self.tasks.clone()
+    }
+    pub fn set_tasks(&mut self, tasks: Rc<RefCell<TaskList>>) {
+        self.tasks = tasks;
+    }
+    pub fn set_notifier(&mut self, notifier: Notifier) {
+        self.notifier = notifier;
+    }
+    pub fn set_hook_sender(&mut self, hook_tx: tokio::sync::mpsc::Sender<Event>) {
+        self.hook_tx = Some(hook_tx);
+    }
+    pub fn get_hook_failure(&self) -> Option<&HookFailure> {
+        self.hook_failure.as_ref()
+    }
+    pub fn set_hook_failure(&mut self, failure: Option<HookFailure>) {
+        self.hook_failure = failure;
+    }
+    /// Runs the configured hooks for `events` in order, in the background. Each event comes with
+    /// the state and iteration it is about.
+    fn run_hooks(&self, events: &[(HookEvent, PomodoroState, u8)]) {
+        let settings = self.settings.borrow();
+        let hooks = events
+            .iter()
+            .filter_map(|(event, state, iteration)| Hook::new(&settings.hooks, *event, *state, *iteration, &self.timer))
+            .collect();
+        Hook::spawn_all(hooks, self.hook_tx.clone());
+    }
+    fn run_transition_hooks(&self, transition: &Transition) {
+        let iteration = self.timer.get_iteration();
+        match *transition {
+            Transition::Next { ended, started } => {
+                // the timer already counts the interval that started
+                let ended_iteration = match started {
+                    PomodoroState::Work(_) => iteration.saturating_sub(1),
+                    _ if !self.timer.get_phases().is_empty() => iteration.saturating_sub(1),
+                    _ => iteration,
+                };
+                let mut events = vec![(HookEvent::end_of(ended), ended, ended_iteration)];
+                // the next interval only starts now if the timer doesn't pause between intervals
+                if self.timer.get_running() {
+                    events.push((HookEvent::start_of(started), started, iteration));
+                }
+                self.run_hooks(&events);
+            }
+            Transition::CycleComplete { ended } => self.run_hooks(&[
+                (HookEvent::end_of(ended), ended, iteration),
+                (HookEvent::CycleComplete, ended, iteration),
+            ]),
+        }
+    }
+    pub fn set_pixela_sender(&mut self, pixela_tx: tokio::sync::mpsc::Sender<PixelaEntry>) {
+        self.pixela_tx = Some(pixela_tx);
+    }
 
         
     pub async fn handle_timer(time_rx: &mut tokio::sync::mpsc::Receiver<i64>, tx: tokio::sync::mpsc::Sender<Event>, cancel_token: CancellationToken) {
@@ -60,30 +207,134 @@ impl Pomodoro {
     }
     pub fn set_time_left(&mut self, time: i64) {
         self.timer.set_time_left(time);
-        if let PomodoroState::Work(_) = self.timer.get_current_state() {
-            self.timer.set_elapsed_time((self.timer.get_iteration()-1) as i64 * Timer::get_duration(&self.timer.get_work_state()) + Timer::get_duration(&self.get_work_state())-time)
+        if let Some(elapsed) = self.timer.profile_elapsed(time) {
+            self.timer.set_elapsed_time(elapsed);
+        }
+        else if let PomodoroState::Work(_) = self.timer.get_current_state() {
+            self.timer.set_elapsed_time((self.timer.get_iteration()-1) as i64 * Timer::get_duration(&self.timer.get_work_state()) + Timer::get_duration(&self.timer.get_current_state())-time)
         }
     }
+    /// Switches profile, starting the cycle over.
+    pub async fn set_phases(&mut self, phases: Vec<Phase>) {
+        self.timer.set_phases(phases);
+        self.timer.restart().await;
+        self.save_snapshot();
+    }
     pub async fn set_setting(&mut self, setting: PomodoroSettings) -> Option<()> {
         self.timer.set_setting(setting).await
     }
-    pub async fn handle_timer_responses(&mut self, time: i64) {
-        if time == -1 && self.timer.get_iteration() < self.timer.get_total_iterations() { 
-            self.timer.next_iteration().await;
+    async fn record_session(&mut self, completed: bool) {
+        let end = Local::now();
+        let state = self.timer.get_current_state();
+        let duration = match completed {
+            true => Timer::get_duration(&state),
+            false => Timer::get_duration(&state) - self.timer.get_timeleft().max(0),
+        };
+        let day = self.timer.get_interval_start().unwrap_or(end).date_naive();
+        let task = match SessionKind::from(state) {
+            SessionKind::Work if completed => {
+                let task = self.tasks.borrow_mut().record_pomodoro();
+                let _ = self.tasks.borrow().save_to_file();
+                task
+            }
+            _ => None,
+        };
+        let session = Session {
+            kind: state.into(),
+            start: self.timer.get_interval_start().unwrap_or(end - chrono::Duration::seconds(duration)),
+            end,
+            duration,
+            iteration: self.timer.get_iteration(),
+            subject: self.timer.get_subject(),
+            task,
+            interruptions: self.timer.get_interruptions().to_vec(),
+            completed,
+        };
+        self.stats.borrow_mut().record(session);
+        // history is flushed right away so a crash doesn't lose finished intervals
+        let _ = self.stats.borrow().save_to_file();
+
+        if let (SessionKind::Work, true, Some(tx)) = (SessionKind::from(state), completed, &self.pixela_tx) {
+            if self.settings.borrow().stats_setting.stats_on {
+                let minutes = self.stats.borrow().focus_on(day) / 60;
+                let _ = tx.send(PixelaEntry::new(day, minutes)).await;
+            }
         }
-        else if time == -1 && self.timer.get_iteration() > self.timer.get_total_iterations() {
-            self.timer.stop().await;
+    }
+    async fn next_iteration(&mut self) -> Option<Transition> {
+        let ended = self.timer.get_current_state();
+        self.timer.next_iteration().await;
+        Some(Transition::Next { ended, started: self.timer.get_current_state() })
+    }
+    async fn complete_cycle(&mut self) -> Option<Transition> {
+        self.timer.stop().await;
+        self.timer.set_completed(true);
+        Some(Transition::CycleComplete { ended: self.timer.get_current_state() })
+    }
+    pub async fn handle_timer_responses(&mut self, time: i64) -> Option<Transition> {
+        let transition = self.advance(time).await;
+        if let Some(transition) = &transition {
+            self.notifier.notify(&Notification::from_transition(transition, &self.timer));
+            self.run_transition_hooks(transition);
         }
-        else if time == -1 && self.timer.get_iteration() == self.timer.get_total_iterations() {
-            if let PomodoroState::Break(_) = self.timer.get_current_state() {
-            self.timer.stop().await;
-            }
+        if transition.is_some() || time % SNAPSHOT_INTERVAL == 0 {
+            self.save_snapshot();
+        }
+        transition
+    }
+    async fn advance(&mut self, time: i64) -> Option<Transition> {
+        if time == -1 {
+            self.record_session(true).await;
+            self.finish_interval().await
         }
-                
         else {
             self.set_time_left(time);
+            None
+        }
+    }
+    async fn finish_interval(&mut self) -> Option<Transition> {
+        match self.timer.get_iteration().cmp(&self.timer.get_total_iterations()) {
+            Ordering::Less => self.next_iteration().await,
+            Ordering::Greater => self.complete_cycle().await,
+            Ordering::Equal => match self.timer.get_current_state() {
+                // a cycle that lines up with the long break interval ends with the long break
+                PomodoroState::Work(_) if self.timer.long_break_due() => self.next_iteration().await,
+                _ => self.complete_cycle().await,
+            },
         }
     }
 }
 
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn finished_cycle_isnt_offered_again() {
+        let dir = std::env::temp_dir().join(format!("romodoro-cycle-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut settings = SettingsTab::default();
+        settings.timer_settings.iterations = 1;
+        let stats = Rc::new(RefCell::new(SessionStore::from_path(dir.join("sessions.json")).unwrap()));
+        let (time_tx, _time_rx) = tokio::sync::mpsc::channel(4);
+        let (command_tx, command_rx) = tokio::sync::mpsc::channel(16);
+        let mut pomodoro = Pomodoro::new(time_tx, command_rx, command_tx, Rc::new(RefCell::new(settings)), stats.clone());
+        pomodoro.set_snapshot_path(dir.join("timer_state.json"));
+
+        pomodoro.cycle().await;
+        pomodoro.handle_timer_responses(SNAPSHOT_INTERVAL).await;
+        assert!(dir.join("timer_state.json").exists());
+        let transition = pomodoro.handle_timer_responses(-1).await;
+        assert!(matches!(transition, Some(Transition::CycleComplete { .. })));
+        assert!(!pomodoro.timer.in_progress());
+        assert!(!dir.join("timer_state.json").exists());
+
+        // going again starts a new cycle instead of finishing the old one twice
+        pomodoro.cycle().await;
+        assert_eq!(stats.borrow().get_sessions().len(), 1);
+        assert_eq!(pomodoro.timer.get_timeleft(), Timer::get_duration(&pomodoro.timer.get_work_state()));
+        assert!(pomodoro.timer.get_running() && !pomodoro.timer.is_completed());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -1,28 +1,73 @@
-use std::{fs, io};
+use std::{collections::BTreeMap, fs, path::{Path, PathBuf}};
 
-use crate::{timer::*, DEFAULT_BREAK, DEFAULT_ITERATIONS, DEFAULT_WORK, WORK_TIME_INCR, BREAK_TIME_INCR};
+use crate::{timer::*, DEFAULT_BREAK, DEFAULT_ITERATIONS, DEFAULT_LONG_BREAK, DEFAULT_LONG_BREAK_INTERVAL, DEFAULT_WORK, WORK_TIME_INCR, BREAK_TIME_INCR, LONG_BREAK_TIME_INCR, PIXELA_GRAPH, PIXELA_URL};
 use serde::*;
 use directories::ProjectDirs;
 use crate::error::{SettingsError,Result};
+use crate::notify::NotificationSettings;
+use crate::keymap::{Keymap, KeymapSettings};
+use crate::theme::{ColorSupport, Theme, ThemeSettings, DEFAULT_THEME};
+use crate::font::{Font, BUILTIN_FONTS};
+use crate::import::ColumnMapping;
+use crate::hooks::HookSettings;
+use crate::profile::{Phase, Profile};
 
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct SettingsTab {
     pub selected_setting: usize,
     pub ui_settings: UISettings,
     pub timer_settings: TimerSettings,
     pub stats_setting: StatsSettings,
+    pub keymap: KeymapSettings,
+    /// Column mappings for `romodoro import --mapping <name>`.
+    pub import_mappings: BTreeMap<String, ColumnMapping>,
+    pub hooks: HookSettings,
+    /// Timer profiles by name, picked with `timer_settings.profile`.
+    pub profiles: BTreeMap<String, Profile>,
+    /// Named timer settings, e.g. `[presets.coding]`, loaded into `timer_settings` when picked.
+    pub presets: BTreeMap<String, TimerSettings>,
+    /// Preset `timer_settings` were last loaded from.
+    pub preset: Option<String>,
 }
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct UISettings {
     pub pause_after_state_change: bool,
     pub hide_work_countdown: bool,
+    pub notifications: NotificationSettings,
+    /// Name of a built-in theme or one from `themes`.
+    pub theme: String,
+    pub themes: BTreeMap<String, ThemeSettings>,
+    /// Clock font: `auto` picks the biggest one that fits, or `compact`, `classic`, `block` or `custom`.
+    pub font: String,
+    /// Font file used by `custom`, relative to the config file.
+    pub font_file: Option<PathBuf>,
+    #[serde(skip)]
+    pub custom_font: Option<Font>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct TimerSettings {
     pub work_time : i64,
     pub break_time : i64,
     pub iterations : u8,
+    pub long_break_time : i64,
+    /// Take a long break after every this many work iterations, 0 turns long breaks off.
+    pub long_break_interval : u8,
+    /// Profile to step through instead of alternating work and breaks.
+    pub profile: Option<String>,
+}
+
+/// Settings rows before the subject list starts.
+const SUBJECT_ROWS_START: usize = 10;
+
+/// Text typed into the subject or task popup, `editing` is the one being renamed.
+#[derive(Debug, Clone, Default)]
+pub struct SubjectInput {
+    pub text: String,
+    pub editing: Option<usize>,
 }
 
 #[derive(Debug, Clone, PartialEq, Copy)]
@@ -30,38 +75,150 @@ pub enum PomodoroSettings {
     WorkTime(Option<i64>),
     BreakTime(Option<i64>),
     Iterations(Option<u8>),
+    LongBreakTime(Option<i64>),
+    LongBreakInterval(Option<u8>),
 }
         
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct StatsSettings {
     pub stats_on: bool,
     pub pixela_username: Option<String>,
     pub subjects: Vec<String>,
+    pub pixela_graph: String,
+    pub pixela_base_url: String,
 }
         
 impl SettingsTab {
-    pub fn save_to_file(&self) -> Result<()> {
+    pub fn config_path() -> Result<PathBuf> {
         let path = ProjectDirs::from("romodoro","mejxedev", "romodoro").ok_or(SettingsError::HomeDirNotFound)?;
-        let path = path.config_dir();
-        if !path.exists() {fs::create_dir(&path)?}
+        Ok(path.config_dir().join("config.toml"))
+    }
+    pub fn save_to_file(&self) -> Result<()> {
+        self.save_to_path(SettingsTab::config_path()?)
+    }
+    pub fn save_to_path(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {fs::create_dir_all(dir)?}
+        }
         let toml_cfg: String = toml::to_string(&self).expect("Settings should be instantiated correctly");
-        fs::write(path.join("config.toml"), toml_cfg)?;
+        fs::write(path, toml_cfg)?;
         Ok(())
     }
     pub fn new() -> Result<SettingsTab> {
-        let path = ProjectDirs::from("romodoro","mejxedev", "romodoro").ok_or(SettingsError::HomeDirNotFound)?;
-        let path = path.config_dir();
-        let config: SettingsTab;
-
-        match &fs::read_to_string(path.join("config.toml")) {
-            Ok(data) => {config = toml::from_str(data).unwrap_or_default()},
-            Err(_) => {config = SettingsTab::default()}
+        SettingsTab::from_path(SettingsTab::config_path()?)
+    }
+    pub fn from_path(path: impl AsRef<Path>) -> Result<SettingsTab> {
+        let path = path.as_ref();
+        let mut config: SettingsTab = match &fs::read_to_string(path) {
+            // defaults would be written over the file on exit, so a broken config is reported
+            Ok(data) => toml::from_str(data).map_err(|e| SettingsError::LoadError(e.to_string()))?,
+            Err(_) => SettingsTab::default()
+        };
+        // a broken keymap could leave no way to quit, so it's reported instead of replaced
+        Keymap::try_from(&config.keymap)?;
+        Theme::named(&config.ui_settings.theme, &config.ui_settings.themes)?;
+        config.ui_settings.load_font(path.parent().unwrap_or(Path::new(".")))?;
+        Profile::validate_all(&config.profiles)?;
+        if let Some(name) = config.timer_settings.profile.as_ref().filter(|name| !config.profiles.contains_key(*name)) {
+            return Err(SettingsError::ProfileError(format!("unknown profile \"{name}\"")).into());
+        }
+        if config.timer_settings.iterations == 0 {
+            return Err(SettingsError::LoadError("iterations must be at least 1".to_string()).into());
+        }
+        for (name, preset) in &config.presets {
+            if preset.iterations == 0 {
+                return Err(SettingsError::PresetError(format!("preset \"{name}\" needs at least 1 iteration")).into());
+            }
+            if let Some(profile) = preset.profile.as_ref().filter(|profile| !config.profiles.contains_key(*profile)) {
+                return Err(SettingsError::PresetError(format!("preset \"{name}\" uses unknown profile \"{profile}\"")).into());
+            }
+        }
+        // only a label, the settings it loaded are still there
+        if config.preset.as_ref().is_some_and(|name| !config.presets.contains_key(name)) {
+            config.preset = None;
         }
         Ok(config)
     }
+    /// Resets what the settings tab shows, notifications and theme definitions are kept.
     pub fn restore_defaults(&mut self) {
         self.timer_settings = TimerSettings::default();
-        self.ui_settings = UISettings::default();
+        self.preset = None;
+        self.ui_settings = UISettings {
+            notifications: std::mem::take(&mut self.ui_settings.notifications),
+            themes: std::mem::take(&mut self.ui_settings.themes),
+            font_file: self.ui_settings.font_file.take(),
+            custom_font: self.ui_settings.custom_font.take(),
+            ..UISettings::default()
+        };
+    }
+    /// The selected theme brought down to what the terminal can show, the default one if it went
+    /// missing since loading.
+    pub fn get_theme(&self) -> Theme {
+        Theme::named(&self.ui_settings.theme, &self.ui_settings.themes)
+            .unwrap_or_default()
+            .adapt(ColorSupport::current())
+    }
+    fn cycle_theme(&mut self, forward: bool) {
+        let names = Theme::names(&self.ui_settings.themes);
+        let current = names.iter().position(|name| *name == self.ui_settings.theme).unwrap_or(0);
+        let next = match forward {
+            true => (current + 1) % names.len(),
+            false => (current + names.len() - 1) % names.len(),
+        };
+        self.ui_settings.theme = names[next].clone();
+    }
+
+    /// Phases of the selected profile, empty for the classic timer.
+    pub fn get_phases(&self) -> Vec<Phase> {
+        self.timer_settings
+            .profile
+            .as_ref()
+            .and_then(|name| self.profiles.get(name))
+            .map(|profile| profile.phases.clone())
+            .unwrap_or_default()
+    }
+    /// Steps through the classic timer followed by every profile.
+    fn cycle_profile(&mut self, forward: bool) {
+        let mut names: Vec<Option<String>> = vec![None];
+        names.extend(self.profiles.keys().cloned().map(Some));
+        let current = names.iter().position(|name| *name == self.timer_settings.profile).unwrap_or(0);
+        let next = match forward {
+            true => (current + 1) % names.len(),
+            false => (current + names.len() - 1) % names.len(),
+        };
+        self.timer_settings.profile = names[next].clone();
+    }
+    /// Loads the preset's values into `timer_settings`, they're applied like edits in the settings tab.
+    pub fn apply_preset(&mut self, name: &str) -> std::result::Result<(), SettingsError> {
+        let preset = self
+            .presets
+            .get(name)
+            .ok_or_else(|| SettingsError::PresetError(format!("unknown preset \"{name}\"")))?;
+        self.timer_settings = preset.clone();
+        self.preset = Some(name.to_string());
+        Ok(())
+    }
+    /// The preset after the current one, or before it, wrapping around. `None` without presets.
+    pub fn preset_after(&self, forward: bool) -> Option<String> {
+        let names: Vec<&String> = self.presets.keys().collect();
+        let next = match self.preset.as_ref().and_then(|preset| names.iter().position(|name| *name == preset)) {
+            Some(current) if forward => (current + 1) % names.len(),
+            Some(current) => (current + names.len() - 1) % names.len(),
+            None if forward => 0,
+            None => names.len().checked_sub(1)?,
+        };
+        names.get(next).map(|name| name.to_string())
+    }
+    /// Whether `timer_settings` were changed since the preset was loaded.
+    pub fn preset_edited(&self) -> bool {
+        self.preset.as_ref().and_then(|name| self.presets.get(name)).is_some_and(|preset| *preset != self.timer_settings)
+    }
+    fn cycle_preset(&mut self, forward: bool) {
+        if let Some(name) = self.preset_after(forward) {
+            let _ = self.apply_preset(&name);
+        }
     }
 
     pub fn get_pomodoro_setting(&self, setting:PomodoroSettings) -> PomodoroSettings {
@@ -75,27 +232,71 @@ impl SettingsTab {
             PomodoroSettings::Iterations(_) => {
                 PomodoroSettings::Iterations(Some(self.timer_settings.iterations))
             }
+            PomodoroSettings::LongBreakTime(_) => {
+                PomodoroSettings::LongBreakTime(Some(self.timer_settings.long_break_time))
+            }
+            PomodoroSettings::LongBreakInterval(_) => {
+                PomodoroSettings::LongBreakInterval(Some(self.timer_settings.long_break_interval))
+            }
 
         }
     }
 
+    fn last_setting(&self) -> usize {
+        SUBJECT_ROWS_START + self.stats_setting.subjects.len() - 1
+    }
     pub fn select_down(&mut self) {
-        if self.selected_setting == 4 {
+        if self.selected_setting >= self.last_setting() {
             self.selected_setting = 0;
         } else { self.selected_setting += 1}
     }
     pub fn select_up(&mut self) {
         if self.selected_setting == 0 {
-            self.selected_setting = 4;
+            self.selected_setting = self.last_setting();
         } else { self.selected_setting -= 1}
     }
+    /// Index into `subjects` of the selected row, if a subject is selected.
+    pub fn get_selected_subject(&self) -> Option<usize> {
+        self.selected_setting.checked_sub(SUBJECT_ROWS_START)
+            .filter(|i| *i < self.stats_setting.subjects.len())
+    }
+    pub fn get_subject_row(index: usize) -> usize {
+        SUBJECT_ROWS_START + index
+    }
+    fn valid_subject(&self, name: &str) -> bool {
+        !name.is_empty() && !self.stats_setting.subjects.iter().any(|s| s == name)
+    }
+    pub fn add_subject(&mut self, name: &str) -> Option<()> {
+        let name = name.trim();
+        if !self.valid_subject(name) {return None}
+        self.stats_setting.subjects.push(name.to_string());
+        self.selected_setting = self.last_setting();
+        Some(())
+    }
+    pub fn rename_subject(&mut self, index: usize, name: &str) -> Option<()> {
+        let name = name.trim();
+        if index >= self.stats_setting.subjects.len() || !self.valid_subject(name) {return None}
+        self.stats_setting.subjects[index] = name.to_string();
+        Some(())
+    }
+    pub fn delete_subject(&mut self, index: usize) -> Option<String> {
+        if index >= self.stats_setting.subjects.len() {return None}
+        let removed = self.stats_setting.subjects.remove(index);
+        self.selected_setting = self.selected_setting.min(self.last_setting());
+        Some(removed)
+    }
     pub fn decrement(&mut self) {
         match self.selected_setting {
-            0 if self.timer_settings.work_time - WORK_TIME_INCR != 0 => {self.timer_settings.work_time -= WORK_TIME_INCR},
-            1 if self.timer_settings.break_time - BREAK_TIME_INCR != 0  => {self.timer_settings.break_time -= BREAK_TIME_INCR},
-            2 if self.timer_settings.iterations - 1 > 0 => {self.timer_settings.iterations -= 1},
-            3 => self.ui_settings.pause_after_state_change = !self.ui_settings.pause_after_state_change,
-            4 => self.ui_settings.hide_work_countdown = !self.ui_settings.hide_work_countdown,
+            0 if self.timer_settings.work_time > WORK_TIME_INCR => {self.timer_settings.work_time -= WORK_TIME_INCR},
+            1 if self.timer_settings.break_time > BREAK_TIME_INCR => {self.timer_settings.break_time -= BREAK_TIME_INCR},
+            2 if self.timer_settings.iterations > 1 => {self.timer_settings.iterations -= 1},
+            3 if self.timer_settings.long_break_time > LONG_BREAK_TIME_INCR => {self.timer_settings.long_break_time -= LONG_BREAK_TIME_INCR},
+            4 if self.timer_settings.long_break_interval > 0 => {self.timer_settings.long_break_interval -= 1},
+            5 => self.cycle_profile(false),
+            6 => self.cycle_preset(false),
+            7 => self.ui_settings.pause_after_state_change = !self.ui_settings.pause_after_state_change,
+            8 => self.ui_settings.hide_work_countdown = !self.ui_settings.hide_work_countdown,
+            9 => self.cycle_theme(false),
             _ => {},
         }
     }
@@ -103,21 +304,63 @@ impl SettingsTab {
         match self.selected_setting {
             0 => {self.timer_settings.work_time += WORK_TIME_INCR},
             1 => {self.timer_settings.break_time += BREAK_TIME_INCR},
-            2 => {self.timer_settings.iterations += 1},
-            3 => self.ui_settings.pause_after_state_change = !self.ui_settings.pause_after_state_change,
-            4 => self.ui_settings.hide_work_countdown = !self.ui_settings.hide_work_countdown,
+            2 => {self.timer_settings.iterations = self.timer_settings.iterations.saturating_add(1)},
+            3 => {self.timer_settings.long_break_time += LONG_BREAK_TIME_INCR},
+            4 => {self.timer_settings.long_break_interval = self.timer_settings.long_break_interval.saturating_add(1)},
+            5 => self.cycle_profile(true),
+            6 => self.cycle_preset(true),
+            7 => self.ui_settings.pause_after_state_change = !self.ui_settings.pause_after_state_change,
+            8 => self.ui_settings.hide_work_countdown = !self.ui_settings.hide_work_countdown,
+            9 => self.cycle_theme(true),
             _ => {}
         }
     }
 }
+impl UISettings {
+    /// Checks the font setting and loads the custom font, `config_dir` is where relative paths start.
+    pub fn load_font(&mut self, config_dir: &Path) -> std::result::Result<(), SettingsError> {
+        match self.font.as_str() {
+            "auto" => Ok(()),
+            name if BUILTIN_FONTS.contains(&name) => Ok(()),
+            "custom" => {
+                let file = self.font_file.as_ref().ok_or_else(|| {
+                    SettingsError::FontError("font is \"custom\" but no font_file is set".to_string())
+                })?;
+                self.custom_font = Some(Font::from_file(config_dir.join(file))?);
+                Ok(())
+            }
+            name => Err(SettingsError::FontError(format!("unknown font \"{name}\""))),
+        }
+    }
+}
 impl Default for UISettings {
     fn default() -> Self {
-        UISettings { pause_after_state_change: false, hide_work_countdown: false}
+        UISettings {
+            pause_after_state_change: false,
+            hide_work_countdown: false,
+            notifications: NotificationSettings::default(),
+            theme: DEFAULT_THEME.to_string(),
+            themes: BTreeMap::new(),
+            font: "auto".to_string(),
+            font_file: None,
+            custom_font: None,
+        }
+    }
+}
+impl Default for StatsSettings {
+    fn default() -> Self {
+        StatsSettings {
+            stats_on: false,
+            pixela_username: None,
+            subjects: Vec::new(),
+            pixela_graph: PIXELA_GRAPH.to_string(),
+            pixela_base_url: PIXELA_URL.to_string(),
+        }
     }
 }
 impl Default for TimerSettings {
     fn default() -> Self {
-        TimerSettings {work_time: DEFAULT_WORK, break_time: DEFAULT_BREAK, iterations: DEFAULT_ITERATIONS}
+        TimerSettings {work_time: DEFAULT_WORK, break_time: DEFAULT_BREAK, iterations: DEFAULT_ITERATIONS, long_break_time: DEFAULT_LONG_BREAK, long_break_interval: DEFAULT_LONG_BREAK_INTERVAL, profile: None}
     }
 }
 impl From<PomodoroState> for PomodoroSettings {
@@ -125,6 +368,7 @@ impl From<PomodoroState> for PomodoroSettings {
         match value {
             PomodoroState::Work(time) => PomodoroSettings::WorkTime(Some(time)),
             PomodoroState::Break(time) => PomodoroSettings::BreakTime(Some(time)),
+            PomodoroState::LongBreak(time) => PomodoroSettings::LongBreakTime(Some(time)),
         }
     }
 }
@@ -137,15 +381,166 @@ impl From<u8> for PomodoroSettings {
 mod tests {
     use super::SettingsTab;
 
+    #[test]
+    fn subjects() {
+        let mut settings = SettingsTab::default();
+        assert!(settings.add_subject(" rust ").is_some());
+        assert!(settings.add_subject("rust").is_none());
+        assert!(settings.add_subject("  ").is_none());
+        assert!(settings.add_subject("maths").is_some());
+        assert_eq!(settings.get_selected_subject(), Some(1));
+
+        assert!(settings.rename_subject(1, "rust").is_none());
+        assert!(settings.rename_subject(1, "physics").is_some());
+        assert_eq!(settings.stats_setting.subjects, vec!["rust", "physics"]);
+
+        settings.select_down();
+        assert_eq!(settings.selected_setting, 0);
+        settings.select_up();
+        assert_eq!(settings.delete_subject(1), Some("physics".to_string()));
+        assert_eq!(settings.get_selected_subject(), Some(0));
+    }
     #[test]
     fn save_to_file() {
+        let path = std::env::temp_dir().join(format!("romodoro-save-{}.toml", std::process::id()));
         let settings = SettingsTab::default();
-        settings.save_to_file();
+        settings.save_to_path(&path).unwrap();
+        assert!(path.exists());
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn keymap_is_validated_on_load() {
+        let path = std::env::temp_dir().join(format!("romodoro-keymap-{}.toml", std::process::id()));
+        let mut settings = SettingsTab::default();
+        settings.keymap.0.insert("skip".to_string(), vec!["s".to_string()]);
+        settings.save_to_path(&path).unwrap();
+        let error = SettingsTab::from_path(&path).unwrap_err();
+        assert!(error.to_string().contains("s is bound to both skip and next_subject"));
+
+        settings.keymap.0.insert("skip".to_string(), vec!["ctrl-s".to_string()]);
+        settings.save_to_path(&path).unwrap();
+        assert_eq!(SettingsTab::from_path(&path).unwrap().keymap, settings.keymap);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn malformed_config_is_rejected() {
+        let path = std::env::temp_dir().join(format!("romodoro-malformed-{}.toml", std::process::id()));
+        std::fs::write(&path, "[keymap]\nquit = \"q\"\n").unwrap();
+        let error = SettingsTab::from_path(&path).unwrap_err().to_string();
+        assert!(error.contains("error with loading your data") && error.contains("invalid type"), "{error}");
+
+        std::fs::write(&path, "[stats_setting]\nsubjects = [\"rust\"]\n").unwrap();
+        assert_eq!(SettingsTab::from_path(&path).unwrap().stats_setting.subjects, vec!["rust"]);
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn profile_is_checked_on_load() {
+        use crate::profile::{Phase, PhaseKind, Profile};
+        let path = std::env::temp_dir().join(format!("romodoro-profile-{}.toml", std::process::id()));
+        let mut settings = SettingsTab::default();
+        settings.timer_settings.profile = Some("deep".to_string());
+        settings.save_to_path(&path).unwrap();
+        assert!(SettingsTab::from_path(&path).unwrap_err().to_string().contains("unknown profile \"deep\""));
+
+        let phases = vec![Phase::new("Warm-up", 300, PhaseKind::Work), Phase::new("Deep work", 3000, PhaseKind::Work)];
+        settings.profiles.insert("deep".to_string(), Profile { phases: phases.clone() });
+        settings.save_to_path(&path).unwrap();
+        let mut loaded = SettingsTab::from_path(&path).unwrap();
+        assert_eq!(loaded.get_phases(), phases);
+        loaded.cycle_profile(true);
+        assert_eq!(loaded.timer_settings.profile, None);
+        assert!(loaded.get_phases().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn presets() {
+        use super::TimerSettings;
+        let mut settings = SettingsTab::default();
+        assert_eq!(settings.preset_after(true), None);
+        settings.presets.insert("admin".to_string(), TimerSettings { work_time: 1500, break_time: 300, ..Default::default() });
+        settings.presets.insert("coding".to_string(), TimerSettings { work_time: 3000, break_time: 600, ..Default::default() });
+        assert_eq!(settings.preset_after(false).as_deref(), Some("coding"));
+
+        settings.selected_setting = 6;
+        settings.increment();
+        assert_eq!((settings.preset.as_deref(), settings.timer_settings.work_time), (Some("admin"), 1500));
+        settings.increment();
+        assert_eq!(settings.preset_after(true).as_deref(), Some("admin"));
+        settings.selected_setting = 0;
+        settings.increment();
+        assert!(settings.preset_edited());
+
+        let path = std::env::temp_dir().join(format!("romodoro-preset-{}.toml", std::process::id()));
+        settings.presets.get_mut("admin").unwrap().profile = Some("deep".to_string());
+        settings.save_to_path(&path).unwrap();
+        assert!(SettingsTab::from_path(&path).unwrap_err().to_string().contains("preset \"admin\" uses unknown profile \"deep\""));
+        let _ = std::fs::remove_file(&path);
+        settings.restore_defaults();
+        assert_eq!(settings.preset, None);
+    }
+    #[test]
+    fn decrement_stays_above_zero() {
+        let mut settings = SettingsTab::default();
+        // e.g. from a preset or `--work 1000s`, not a multiple of the increment
+        settings.timer_settings.work_time = 1000;
+        settings.timer_settings.long_break_time = 90;
+        settings.selected_setting = 0;
+        settings.decrement();
+        settings.decrement();
+        assert_eq!(settings.timer_settings.work_time, 100);
+        settings.selected_setting = 3;
+        settings.decrement();
+        assert_eq!(settings.timer_settings.long_break_time, 90);
+    }
+    #[test]
+    fn iterations_stay_in_range() {
+        use super::TimerSettings;
+        let mut settings = SettingsTab::default();
+        settings.timer_settings.iterations = 1;
+        settings.selected_setting = 2;
+        settings.decrement();
+        assert_eq!(settings.timer_settings.iterations, 1);
+        settings.timer_settings.iterations = u8::MAX;
+        settings.increment();
+        assert_eq!(settings.timer_settings.iterations, u8::MAX);
+        settings.timer_settings.long_break_interval = u8::MAX;
+        settings.selected_setting = 4;
+        settings.increment();
+        assert_eq!(settings.timer_settings.long_break_interval, u8::MAX);
+
+        let path = std::env::temp_dir().join(format!("romodoro-iterations-{}.toml", std::process::id()));
+        settings.timer_settings.iterations = 0;
+        settings.save_to_path(&path).unwrap();
+        assert!(SettingsTab::from_path(&path).unwrap_err().to_string().contains("iterations must be at least 1"));
+        settings.timer_settings.iterations = 4;
+        settings.presets.insert("broken".to_string(), TimerSettings { iterations: 0, ..Default::default() });
+        settings.save_to_path(&path).unwrap();
+        assert!(SettingsTab::from_path(&path).unwrap_err().to_string().contains("preset \"broken\" needs at least 1 iteration"));
+        let _ = std::fs::remove_file(&path);
+    }
+    #[test]
+    fn custom_font_next_to_config() {
+        let dir = std::env::temp_dir().join(format!("romodoro-font-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let font: String = "0123456789:".chars().map(|glyph| format!("[{glyph}]\n{glyph}\n")).collect();
+        std::fs::write(dir.join("digits.txt"), font).unwrap();
+        let mut settings = SettingsTab::default();
+        settings.ui_settings.font = "custom".to_string();
+        settings.save_to_path(dir.join("config.toml")).unwrap();
+        assert!(SettingsTab::from_path(dir.join("config.toml")).unwrap_err().to_string().contains("no font_file"));
+
+        settings.ui_settings.font_file = Some("digits.txt".into());
+        settings.save_to_path(dir.join("config.toml")).unwrap();
+        let loaded = SettingsTab::from_path(dir.join("config.toml")).unwrap();
+        assert_eq!(loaded.ui_settings.custom_font.unwrap().render("12:34"), "1 2 : 3 4");
+        let _ = std::fs::remove_dir_all(&dir);
     }
     #[test]
     fn read() {
-        let settings = SettingsTab::new();
-        dbg!(settings);
+        let path = std::env::temp_dir().join(format!("romodoro-read-{}.toml", std::process::id()));
+        SettingsTab::default().save_to_path(&path).unwrap();
+        dbg!(SettingsTab::from_path(&path).unwrap());
+        let _ = std::fs::remove_file(&path);
     }
 }
 
@@ -0,0 +1,339 @@
+use std::{collections::{BTreeMap, HashMap}, fs, io, path::{Path, PathBuf}};
+
+use chrono::{DateTime, Days, Local, NaiveDate};
+use serde::*;
+
+use crate::error::{Result, StatsError};
+use crate::settings::SettingsTab;
+use crate::timer::PomodoroState;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SessionKind {
+    Work,
+    Break,
+    LongBreak,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InterruptionKind {
+    /// Your own urge to do something else.
+    Internal,
+    /// Someone or something else asking for attention.
+    External,
+}
+
+/// Something that broke focus during a work interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interruption {
+    pub kind: InterruptionKind,
+    pub at: DateTime<Local>,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// A single finished work or break interval.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub kind: SessionKind,
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// Planned length of the interval in seconds.
+    pub duration: i64,
+    pub iteration: u8,
+    pub subject: Option<String>,
+    /// Active task when the interval finished.
+    #[serde(default)]
+    pub task: Option<String>,
+    #[serde(default)]
+    pub interruptions: Vec<Interruption>,
+    /// `false` if the interval was skipped, `duration` is then the time it ran for.
+    #[serde(default = "completed_by_default")]
+    pub completed: bool,
+}
+fn completed_by_default() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsRange {
+    #[default]
+    Week,
+    Month,
+}
+
+/// View state of the stats dashboard.
+#[derive(Debug, Clone, Default)]
+pub struct StatsTab {
+    pub range: StatsRange,
+    /// How many days back from today the shown window ends.
+    pub offset: u64,
+    /// Outcome of the last export.
+    pub message: Option<String>,
+}
+
+/// Session history, kept as `sessions.json` next to `config.toml`.
+#[derive(Debug, Clone)]
+pub struct SessionStore {
+    sessions: Vec<Session>,
+    path: PathBuf,
+}
+
+impl SessionStore {
+    pub fn new() -> Result<SessionStore> {
+        SessionStore::from_path(SessionStore::path_for(&SettingsTab::config_path()?))
+    }
+    /// Where the history goes for the config at `config_path`.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_file_name("sessions.json")
+    }
+    pub fn from_path(path: impl AsRef<Path>) -> Result<SessionStore> {
+        let path = path.as_ref().to_path_buf();
+        let sessions = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| StatsError::LoadError(e.to_string()))?,
+            // anything else would have the history overwritten on the next save
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(SessionStore { sessions, path })
+    }
+    pub fn save_to_file(&self) -> Result<()> {
+        if let Some(dir) = self.path.parent() {
+            if !dir.exists() {fs::create_dir_all(dir)?}
+        }
+        let json = serde_json::to_string_pretty(&self.sessions).map_err(|e| StatsError::SaveError(e.to_string()))?;
+        fs::write(&self.path, json)?;
+        Ok(())
+    }
+    pub fn record(&mut self, session: Session) {
+        self.sessions.push(session);
+    }
+    /// Adds sessions that don't overlap one of the same kind and returns how many went in.
+    pub fn merge(&mut self, sessions: Vec<Session>) -> usize {
+        let before = self.sessions.len();
+        for session in sessions {
+            let overlaps = self
+                .sessions
+                .iter()
+                .any(|other| other.kind == session.kind && other.start < session.end && session.start < other.end);
+            if !overlaps {
+                self.sessions.push(session);
+            }
+        }
+        self.sessions.sort_by_key(|session| session.start);
+        self.sessions.len() - before
+    }
+    pub fn get_sessions(&self) -> &[Session] {
+        &self.sessions
+    }
+    pub fn get_path(&self) -> &Path {
+        &self.path
+    }
+    /// Finished work intervals, skipped ones don't count as focus.
+    fn work_sessions(&self) -> impl Iterator<Item = &Session> {
+        self.sessions.iter().filter(|s| s.kind == SessionKind::Work && s.completed)
+    }
+    /// Focused seconds on each day from `from` to `to`, both inclusive.
+    pub fn focus_per_day(&self, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, i64)> {
+        let mut days: BTreeMap<NaiveDate, i64> = from.iter_days().take_while(|d| *d <= to).map(|d| (d, 0)).collect();
+        for session in self.work_sessions() {
+            if let Some(total) = days.get_mut(&session.start.date_naive()) {
+                *total += session.duration;
+            }
+        }
+        days.into_iter().collect()
+    }
+    pub fn focus_on(&self, day: NaiveDate) -> i64 {
+        self.work_sessions().filter(|s| s.start.date_naive() == day).map(|s| s.duration).sum()
+    }
+    /// Focused seconds per subject from `from` to `to`, biggest first.
+    pub fn focus_by_subject(&self, from: NaiveDate, to: NaiveDate) -> Vec<(String, i64)> {
+        let mut subjects: HashMap<String, i64> = HashMap::new();
+        for session in self.work_sessions().filter(|s| (from..=to).contains(&s.start.date_naive())) {
+            let subject = session.subject.clone().unwrap_or("No subject".to_string());
+            *subjects.entry(subject).or_default() += session.duration;
+        }
+        let mut subjects: Vec<(String, i64)> = subjects.into_iter().collect();
+        subjects.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        subjects
+    }
+    /// Internal and external interruptions logged from `from` to `to`.
+    pub fn interruptions(&self, from: NaiveDate, to: NaiveDate) -> (usize, usize) {
+        let interruptions: Vec<&Interruption> = self
+            .sessions
+            .iter()
+            .filter(|s| (from..=to).contains(&s.start.date_naive()))
+            .flat_map(|s| &s.interruptions)
+            .collect();
+        let internal = interruptions.iter().filter(|i| i.kind == InterruptionKind::Internal).count();
+        (internal, interruptions.len() - internal)
+    }
+    /// Returns (current, longest) streak of days with at least one finished work interval.
+    /// The current streak is still alive if today has no sessions yet but yesterday had.
+    pub fn streaks(&self, today: NaiveDate) -> (u32, u32) {
+        let mut days: Vec<NaiveDate> = self.work_sessions().map(|s| s.start.date_naive()).collect();
+        days.sort();
+        days.dedup();
+
+        let mut longest = 0;
+        let mut run = 0;
+        let mut previous: Option<NaiveDate> = None;
+        for day in &days {
+            run = match previous {
+                Some(prev) if prev.succ_opt() == Some(*day) => run + 1,
+                _ => 1,
+            };
+            longest = longest.max(run);
+            previous = Some(*day);
+        }
+
+        let yesterday = today.pred_opt().unwrap_or(today);
+        let current = match days.last() {
+            Some(last) if *last == today || *last == yesterday => run,
+            _ => 0,
+        };
+        (current, longest)
+    }
+}
+
+impl StatsTab {
+    pub fn toggle_range(&mut self) {
+        self.range = match self.range {
+            StatsRange::Week => StatsRange::Month,
+            StatsRange::Month => StatsRange::Week,
+        };
+    }
+    pub fn scroll_back(&mut self) {
+        self.offset += 1;
+    }
+    pub fn scroll_forward(&mut self) {
+        self.offset = self.offset.saturating_sub(1);
+    }
+    /// First and last day of the window currently shown.
+    pub fn get_window(&self, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+        let end = today.checked_sub_days(Days::new(self.offset)).unwrap_or(today);
+        let start = end.checked_sub_days(Days::new(self.range.days() - 1)).unwrap_or(end);
+        (start, end)
+    }
+}
+
+impl StatsRange {
+    pub fn days(&self) -> u64 {
+        match self {
+            StatsRange::Week => 7,
+            StatsRange::Month => 30,
+        }
+    }
+}
+
+impl From<PomodoroState> for SessionKind {
+    fn from(value: PomodoroState) -> Self {
+        match value {
+            PomodoroState::Work(_) => SessionKind::Work,
+            PomodoroState::Break(_) => SessionKind::Break,
+            PomodoroState::LongBreak(_) => SessionKind::LongBreak,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> SessionStore {
+        let path = std::env::temp_dir().join(format!("romodoro-{}-{}.json", name, std::process::id()));
+        let _ = fs::remove_file(&path);
+        SessionStore::from_path(path).unwrap()
+    }
+
+    #[test]
+    fn missing_file_is_empty() {
+        let store = temp_store("missing");
+        assert!(store.get_sessions().is_empty());
+        // a history that can't be read isn't an empty one
+        assert!(SessionStore::from_path(std::env::temp_dir()).is_err());
+    }
+    #[test]
+    fn save_and_load() {
+        let mut store = temp_store("roundtrip");
+        let end = Local::now();
+        let session = Session {
+            kind: SessionKind::Work,
+            start: end - chrono::Duration::seconds(1500),
+            end,
+            duration: 1500,
+            iteration: 1,
+            subject: Some("rust".to_string()),
+            task: None,
+            interruptions: vec![Interruption { kind: InterruptionKind::External, at: end, note: Some("phone".to_string()) }],
+            completed: true,
+        };
+        store.record(session.clone());
+        store.save_to_file().unwrap();
+        let loaded = SessionStore::from_path(store.get_path()).unwrap();
+        assert_eq!(loaded.get_sessions(), &[session]);
+        let _ = fs::remove_file(store.get_path());
+    }
+    fn work_on(day: NaiveDate, minutes: i64, subject: Option<&str>) -> Session {
+        let start = day.and_hms_opt(12, 0, 0).unwrap().and_local_timezone(Local).unwrap();
+        Session {
+            kind: SessionKind::Work,
+            start,
+            end: start + chrono::Duration::minutes(minutes),
+            duration: minutes * 60,
+            iteration: 1,
+            subject: subject.map(String::from),
+            task: None,
+            interruptions: Vec::new(),
+            completed: true,
+        }
+    }
+    #[test]
+    fn aggregates() {
+        let mut store = temp_store("aggregates");
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let day = |n: u64| today.checked_sub_days(Days::new(n)).unwrap();
+        for n in [0, 1, 2, 5, 6, 7, 8] {
+            store.record(work_on(day(n), 25, Some("rust")));
+        }
+        store.record(work_on(today, 50, None));
+
+        assert_eq!(store.focus_on(today), 75 * 60);
+        assert_eq!(store.streaks(today), (3, 4));
+        assert_eq!(store.streaks(day(3)), (0, 4));
+
+        let per_day = store.focus_per_day(day(3), today);
+        assert_eq!(per_day.len(), 4);
+        assert_eq!(per_day[0], (day(3), 0));
+        assert_eq!(per_day[3], (today, 75 * 60));
+
+        let subjects = store.focus_by_subject(day(1), today);
+        assert_eq!(subjects, vec![("No subject".to_string(), 50 * 60), ("rust".to_string(), 50 * 60)]);
+
+        let mut interrupted = work_on(day(1), 25, None);
+        for kind in [InterruptionKind::Internal, InterruptionKind::External, InterruptionKind::Internal] {
+            interrupted.interruptions.push(Interruption { kind, at: interrupted.start, note: None });
+        }
+        store.record(interrupted);
+        assert_eq!(store.interruptions(day(1), today), (2, 1));
+        assert_eq!(store.interruptions(today, today), (0, 0));
+    }
+    #[test]
+    fn stats_window() {
+        let today = NaiveDate::from_ymd_opt(2025, 3, 10).unwrap();
+        let mut tab = StatsTab::default();
+        assert_eq!(tab.get_window(today), (NaiveDate::from_ymd_opt(2025, 3, 4).unwrap(), today));
+        tab.toggle_range();
+        tab.scroll_back();
+        assert_eq!(tab.get_window(today), (NaiveDate::from_ymd_opt(2025, 2, 8).unwrap(), NaiveDate::from_ymd_opt(2025, 3, 9).unwrap()));
+        tab.scroll_forward();
+        tab.scroll_forward();
+        assert_eq!(tab.offset, 0);
+    }
+    #[test]
+    fn corrupt_file_is_an_error() {
+        let store = temp_store("corrupt");
+        fs::write(store.get_path(), "not json").unwrap();
+        assert!(SessionStore::from_path(store.get_path()).is_err());
+        let _ = fs::remove_file(store.get_path());
+    }
+}
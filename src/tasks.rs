@@ -0,0 +1,257 @@
+use std::{fs, io, path::{Path, PathBuf}};
+
+use serde::*;
+
+use crate::error::{Result, TaskError};
+
+/// Most pomodoros a task can be estimated at.
+pub const MAX_ESTIMATE: u8 = 99;
+
+/// Something to work on, with how many pomodoros it should take.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Task {
+    pub name: String,
+    /// Planned work intervals.
+    pub estimate: u8,
+    /// Finished work intervals.
+    #[serde(default)]
+    pub pomodoros: u8,
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// Task list, kept as `tasks.json` next to `config.toml`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TaskList {
+    tasks: Vec<Task>,
+    /// Task finished work intervals count toward.
+    active: Option<usize>,
+    #[serde(skip)]
+    selected: usize,
+    /// `None` keeps the list in memory only.
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Task {
+    pub fn new(name: &str) -> Task {
+        Task { name: name.to_string(), estimate: 1, pomodoros: 0, done: false }
+    }
+}
+
+impl TaskList {
+    /// Where tasks go for the config at `config_path`.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_file_name("tasks.json")
+    }
+    pub fn from_path(path: impl AsRef<Path>) -> Result<TaskList> {
+        let path = path.as_ref().to_path_buf();
+        let mut list: TaskList = match fs::read_to_string(&path) {
+            Ok(data) => serde_json::from_str(&data).map_err(|e| TaskError::LoadError(e.to_string()))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => TaskList::default(),
+            Err(e) => return Err(e.into()),
+        };
+        // the active task has to exist and still be open
+        if list.active.is_some_and(|i| list.tasks.get(i).is_none_or(|task| task.done)) {
+            list.active = None;
+        }
+        list.path = Some(path);
+        Ok(list)
+    }
+    pub fn save_to_file(&self) -> Result<()> {
+        let Some(path) = &self.path else {return Ok(())};
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {fs::create_dir_all(dir)?}
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| TaskError::SaveError(e.to_string()))?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+    /// Adds a task below the others and selects it. Blank names are ignored.
+    pub fn add(&mut self, name: &str) -> Option<usize> {
+        let name = name.trim();
+        if name.is_empty() {return None}
+        self.tasks.push(Task::new(name));
+        self.selected = self.tasks.len() - 1;
+        Some(self.selected)
+    }
+    pub fn rename(&mut self, index: usize, name: &str) -> Option<()> {
+        let name = name.trim();
+        if name.is_empty() {return None}
+        self.tasks.get_mut(index)?.name = name.to_string();
+        Some(())
+    }
+    pub fn delete(&mut self, index: usize) -> Option<Task> {
+        if index >= self.tasks.len() {return None}
+        let task = self.tasks.remove(index);
+        self.active = match self.active {
+            Some(active) if active == index => None,
+            Some(active) if active > index => Some(active - 1),
+            active => active,
+        };
+        self.selected = self.selected.min(self.tasks.len().saturating_sub(1));
+        Some(task)
+    }
+    /// Moves the selected task up or down a row, the selection moves with it.
+    pub fn move_selected(&mut self, up: bool) {
+        let from = self.selected;
+        let to = match up {
+            true if from > 0 => from - 1,
+            false if from + 1 < self.tasks.len() => from + 1,
+            _ => return,
+        };
+        self.tasks.swap(from, to);
+        self.active = self.active.map(|active| match active {
+            a if a == from => to,
+            a if a == to => from,
+            a => a,
+        });
+        self.selected = to;
+    }
+    /// Marks the task done or not done. A finished task stops being active.
+    pub fn toggle_done(&mut self, index: usize) {
+        let Some(task) = self.tasks.get_mut(index) else {return};
+        task.done = !task.done;
+        if task.done && self.active == Some(index) {
+            self.active = None;
+        }
+    }
+    /// Makes the task active, or inactive if it already is. Finished tasks can't be active.
+    pub fn toggle_active(&mut self, index: usize) {
+        match self.tasks.get(index) {
+            Some(_) if self.active == Some(index) => self.active = None,
+            Some(task) if !task.done => self.active = Some(index),
+            _ => {}
+        }
+    }
+    /// Activates the next unfinished task, wrapping around to no task.
+    pub fn next_active(&mut self) {
+        let start = self.active.map_or(0, |i| i + 1);
+        self.active = (start..self.tasks.len()).find(|i| !self.tasks[*i].done);
+    }
+    pub fn change_estimate(&mut self, index: usize, increase: bool) {
+        let Some(task) = self.tasks.get_mut(index) else {return};
+        task.estimate = match increase {
+            true => (task.estimate + 1).min(MAX_ESTIMATE),
+            false => task.estimate.saturating_sub(1).max(1),
+        };
+    }
+    /// Counts a finished work interval toward the active task and returns its name.
+    pub fn record_pomodoro(&mut self) -> Option<String> {
+        let task = self.tasks.get_mut(self.active?)?;
+        task.pomodoros = task.pomodoros.saturating_add(1);
+        Some(task.name.clone())
+    }
+    pub fn select_up(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+    pub fn select_down(&mut self) {
+        if self.selected + 1 < self.tasks.len() {
+            self.selected += 1;
+        }
+    }
+    pub fn get_tasks(&self) -> &[Task] {
+        &self.tasks
+    }
+    pub fn get_active_index(&self) -> Option<usize> {
+        self.active
+    }
+    pub fn get_active(&self) -> Option<&Task> {
+        self.tasks.get(self.active?)
+    }
+    /// Index of the selected task, `None` if there are no tasks.
+    pub fn get_selected(&self) -> Option<usize> {
+        (self.selected < self.tasks.len()).then_some(self.selected)
+    }
+    /// Unfinished tasks.
+    pub fn remaining(&self) -> usize {
+        self.tasks.iter().filter(|task| !task.done).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(names: &[&str]) -> TaskList {
+        let mut list = TaskList::default();
+        for name in names {
+            list.add(name);
+        }
+        list
+    }
+    fn names(list: &TaskList) -> Vec<&str> {
+        list.get_tasks().iter().map(|task| task.name.as_str()).collect()
+    }
+
+    #[test]
+    fn edit_and_reorder() {
+        let mut list = list(&["write", "review", " ", "deploy"]);
+        assert_eq!(names(&list), ["write", "review", "deploy"]);
+        assert_eq!(list.get_selected(), Some(2));
+
+        list.toggle_active(0);
+        list.move_selected(true);
+        assert_eq!(names(&list), ["write", "deploy", "review"]);
+        assert_eq!(list.get_selected(), Some(1));
+        list.move_selected(true);
+        list.move_selected(true);
+        assert_eq!(names(&list), ["deploy", "write", "review"]);
+        // the active task follows its row
+        assert_eq!(list.get_active().unwrap().name, "write");
+
+        list.delete(0);
+        assert_eq!(list.get_active_index(), Some(0));
+        list.rename(1, "code review");
+        list.change_estimate(1, true);
+        list.change_estimate(0, false);
+        assert_eq!(list.get_tasks()[1].estimate, 2);
+        assert_eq!(list.get_tasks()[0].estimate, 1);
+        list.delete(0);
+        assert_eq!(list.get_active(), None);
+        assert_eq!(names(&list), ["code review"]);
+    }
+    #[test]
+    fn pomodoros_count_toward_active_task() {
+        let mut list = list(&["write", "review", "deploy"]);
+        assert_eq!(list.record_pomodoro(), None);
+        list.toggle_done(1);
+        list.next_active();
+        assert_eq!(list.record_pomodoro().as_deref(), Some("write"));
+        // finished tasks are skipped, then there's no active task
+        list.next_active();
+        assert_eq!(list.get_active().unwrap().name, "deploy");
+        list.next_active();
+        assert_eq!(list.get_active(), None);
+        list.toggle_active(1);
+        assert_eq!(list.get_active(), None);
+
+        list.toggle_active(0);
+        list.record_pomodoro();
+        list.toggle_done(0);
+        assert_eq!(list.get_active(), None);
+        assert_eq!(list.get_tasks()[0].pomodoros, 2);
+        assert_eq!(list.remaining(), 1);
+    }
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("romodoro-tasks-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut tasks = TaskList::from_path(&path).unwrap();
+        assert!(tasks.get_tasks().is_empty());
+        assert!(TaskList::from_path(std::env::temp_dir()).is_err());
+        tasks.add("write");
+        tasks.add("review");
+        tasks.toggle_active(1);
+        tasks.save_to_file().unwrap();
+
+        let loaded = TaskList::from_path(&path).unwrap();
+        assert_eq!(loaded.get_tasks(), tasks.get_tasks());
+        assert_eq!(loaded.get_active().unwrap().name, "review");
+
+        tasks.tasks[1].done = true;
+        tasks.save_to_file().unwrap();
+        assert_eq!(TaskList::from_path(&path).unwrap().get_active(), None);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,367 @@
+use std::{collections::BTreeMap, str::FromStr, sync::OnceLock};
+
+use ratatui::style::Color;
+use serde::*;
+
+use crate::error::SettingsError;
+use crate::timer::PomodoroState;
+
+pub const DEFAULT_THEME: &str = "gruvbox";
+/// Built-in themes in the order the settings tab cycles through them.
+pub const BUILTIN_THEMES: [&str; 4] = ["gruvbox", "solarized", "nord", "ansi"];
+
+/// Colours every widget draws with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Main borders, the clock and the selected setting.
+    pub accent: Color,
+    pub work: Color,
+    pub short_break: Color,
+    pub long_break: Color,
+    /// Warnings and the hidden clock.
+    pub alert: Color,
+    /// Painted under everything that doesn't set its own background, `reset` keeps the terminal's.
+    pub background: Color,
+    pub text: Color,
+    pub muted: Color,
+    /// The selected tab.
+    pub highlight: Color,
+}
+
+/// What the terminal can show, so truecolor palettes can be brought down to it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ColorSupport {
+    TrueColor,
+    Indexed256,
+    Ansi16,
+    /// `NO_COLOR` is set, everything is drawn in the terminal's own colours.
+    NoColor,
+}
+
+/// A theme defined in the config. Colours are names like `lightblue`, `#rrggbb` or a 0-255 index,
+/// the ones left out are taken from the built-in `base` theme.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub base: Option<String>,
+    pub accent: Option<String>,
+    pub work: Option<String>,
+    pub short_break: Option<String>,
+    pub long_break: Option<String>,
+    pub alert: Option<String>,
+    pub background: Option<String>,
+    pub text: Option<String>,
+    pub muted: Option<String>,
+    pub highlight: Option<String>,
+}
+
+impl Theme {
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let theme = match name {
+            "gruvbox" => Theme {
+                accent: Color::Rgb(215, 153, 33),
+                work: Color::Rgb(69, 133, 136),
+                short_break: Color::Rgb(142, 192, 124),
+                long_break: Color::Rgb(177, 98, 134),
+                alert: Color::Rgb(204, 36, 29),
+                background: Color::Rgb(40, 40, 40),
+                text: Color::White,
+                muted: Color::Gray,
+                highlight: Color::Rgb(240, 94, 90),
+            },
+            "solarized" => Theme {
+                accent: Color::Rgb(181, 137, 0),
+                work: Color::Rgb(38, 139, 210),
+                short_break: Color::Rgb(133, 153, 0),
+                long_break: Color::Rgb(108, 113, 196),
+                alert: Color::Rgb(220, 50, 47),
+                background: Color::Rgb(0, 43, 54),
+                text: Color::Rgb(238, 232, 213),
+                muted: Color::Rgb(131, 148, 150),
+                highlight: Color::Rgb(203, 75, 22),
+            },
+            "nord" => Theme {
+                accent: Color::Rgb(235, 203, 139),
+                work: Color::Rgb(136, 192, 208),
+                short_break: Color::Rgb(163, 190, 140),
+                long_break: Color::Rgb(180, 142, 173),
+                alert: Color::Rgb(191, 97, 106),
+                background: Color::Rgb(46, 52, 64),
+                text: Color::Rgb(236, 239, 244),
+                muted: Color::Rgb(216, 222, 233),
+                highlight: Color::Rgb(208, 135, 112),
+            },
+            // only the 16 ANSI colours, for terminals without 256 colours or truecolor
+            "ansi" => Theme {
+                accent: Color::Yellow,
+                work: Color::Blue,
+                short_break: Color::Green,
+                long_break: Color::Magenta,
+                alert: Color::Red,
+                background: Color::Reset,
+                text: Color::White,
+                muted: Color::Gray,
+                highlight: Color::LightRed,
+            },
+            _ => return None,
+        };
+        Some(theme)
+    }
+    /// Built-in themes followed by the ones from the config.
+    pub fn names(custom: &BTreeMap<String, ThemeSettings>) -> Vec<String> {
+        let mut names: Vec<String> = BUILTIN_THEMES.iter().map(|name| name.to_string()).collect();
+        names.extend(custom.keys().filter(|name| !BUILTIN_THEMES.contains(&name.as_str())).cloned());
+        names
+    }
+    /// Looks `name` up in the config first, so a built-in theme can be redefined.
+    pub fn named(name: &str, custom: &BTreeMap<String, ThemeSettings>) -> Result<Theme, SettingsError> {
+        let Some(settings) = custom.get(name) else {
+            return Theme::builtin(name).ok_or_else(|| SettingsError::ThemeError(format!("unknown theme \"{name}\"")));
+        };
+        let base_name = settings.base.as_deref().unwrap_or(DEFAULT_THEME);
+        let base = Theme::builtin(base_name).ok_or_else(|| {
+            SettingsError::ThemeError(format!("theme \"{name}\" is based on \"{base_name}\", which isn't a built-in theme"))
+        })?;
+        let colour = |value: &Option<String>, field: &str, fallback: Color| match value {
+            Some(value) => Color::from_str(value)
+                .map_err(|_| SettingsError::ThemeError(format!("invalid colour \"{value}\" for {field} in theme \"{name}\""))),
+            None => Ok(fallback),
+        };
+        Ok(Theme {
+            accent: colour(&settings.accent, "accent", base.accent)?,
+            work: colour(&settings.work, "work", base.work)?,
+            short_break: colour(&settings.short_break, "short_break", base.short_break)?,
+            long_break: colour(&settings.long_break, "long_break", base.long_break)?,
+            alert: colour(&settings.alert, "alert", base.alert)?,
+            background: colour(&settings.background, "background", base.background)?,
+            text: colour(&settings.text, "text", base.text)?,
+            muted: colour(&settings.muted, "muted", base.muted)?,
+            highlight: colour(&settings.highlight, "highlight", base.highlight)?,
+        })
+    }
+    /// Maps every colour to what `support` can show.
+    pub fn adapt(self, support: ColorSupport) -> Theme {
+        let theme = self.map(|colour| support.adapt(colour));
+        match support {
+            // the bright background colours blink on the Linux console
+            ColorSupport::Ansi16 => Theme { background: Color::Reset, ..theme },
+            _ => theme,
+        }
+    }
+    fn map(self, f: impl Fn(Color) -> Color) -> Theme {
+        Theme {
+            accent: f(self.accent),
+            work: f(self.work),
+            short_break: f(self.short_break),
+            long_break: f(self.long_break),
+            alert: f(self.alert),
+            background: f(self.background),
+            text: f(self.text),
+            muted: f(self.muted),
+            highlight: f(self.highlight),
+        }
+    }
+    pub fn state(&self, state: &PomodoroState) -> Color {
+        match state {
+            PomodoroState::Work(_) => self.work,
+            PomodoroState::Break(_) => self.short_break,
+            PomodoroState::LongBreak(_) => self.long_break,
+        }
+    }
+}
+impl ColorSupport {
+    /// Detected once from the environment.
+    pub fn current() -> ColorSupport {
+        static SUPPORT: OnceLock<ColorSupport> = OnceLock::new();
+        *SUPPORT.get_or_init(|| ColorSupport::from_env(|name| std::env::var(name).ok()))
+    }
+    pub fn from_env(var: impl Fn(&str) -> Option<String>) -> ColorSupport {
+        // https://no-color.org: set and not empty
+        if var("NO_COLOR").is_some_and(|value| !value.is_empty()) {
+            return ColorSupport::NoColor;
+        }
+        let colorterm = var("COLORTERM").unwrap_or_default().to_lowercase();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            return ColorSupport::TrueColor;
+        }
+        let term = var("TERM").unwrap_or_default().to_lowercase();
+        // Windows and GUI-launched shells often leave TERM unset, they keep the full colours
+        if term.is_empty() || term.contains("truecolor") || term.contains("24bit") || term.ends_with("-direct") {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Indexed256
+        } else if term == "dumb" {
+            ColorSupport::NoColor
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+    pub fn adapt(&self, colour: Color) -> Color {
+        match (self, colour) {
+            (ColorSupport::NoColor, _) => Color::Reset,
+            (ColorSupport::TrueColor, colour) => colour,
+            (ColorSupport::Indexed256, Color::Rgb(r, g, b)) => Color::Indexed(nearest_indexed(r, g, b)),
+            (ColorSupport::Ansi16, Color::Rgb(r, g, b)) => nearest_ansi(r, g, b),
+            (ColorSupport::Ansi16, Color::Indexed(index)) => match index {
+                0..=15 => ANSI[index as usize].0,
+                _ => {
+                    let (r, g, b) = indexed_rgb(index);
+                    nearest_ansi(r, g, b)
+                }
+            },
+            (_, colour) => colour,
+        }
+    }
+}
+
+/// The 16 ANSI colours in index order with the xterm defaults.
+const ANSI: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI[index as usize].1,
+        16..=231 => {
+            let i = index - 16;
+            (CUBE_LEVELS[(i / 36) as usize], CUBE_LEVELS[(i / 6 % 6) as usize], CUBE_LEVELS[(i % 6) as usize])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+/// Closest colour of the xterm 6x6x6 cube or grey ramp.
+fn nearest_indexed(r: u8, g: u8, b: u8) -> u8 {
+    let level = |c: u8| (0..6).min_by_key(|i| (CUBE_LEVELS[*i] as i32 - c as i32).abs()).expect("Levels aren't empty") as u8;
+    let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+    let average = (r as u16 + g as u16 + b as u16) / 3;
+    let grey = 232 + ((average.saturating_sub(3) / 10).min(23)) as u8;
+    match distance(indexed_rgb(grey), (r, g, b)) < distance(indexed_rgb(cube), (r, g, b)) {
+        true => grey,
+        false => cube,
+    }
+}
+// Plain distance turns most muted theme colours grey, so the hue picks the colour and the
+// lightness picks the normal or bright variant.
+fn nearest_ansi(r: u8, g: u8, b: u8) -> Color {
+    let (rf, gf, bf) = (r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0);
+    let max = rf.max(gf).max(bf);
+    let min = rf.min(gf).min(bf);
+    let chroma = max - min;
+    let lightness = (max + min) / 2.0;
+    if chroma < 0.1 {
+        return match lightness {
+            l if l < 0.2 => Color::Black,
+            l if l < 0.45 => Color::DarkGray,
+            l if l < 0.8 => Color::Gray,
+            _ => Color::White,
+        };
+    }
+    let hue = match max {
+        m if m == rf => 60.0 * ((gf - bf) / chroma).rem_euclid(6.0),
+        m if m == gf => 60.0 * ((bf - rf) / chroma + 2.0),
+        _ => 60.0 * ((rf - gf) / chroma + 4.0),
+    };
+    let bright = lightness > 0.6;
+    let (normal, light) = match hue {
+        h if !(20.0..345.0).contains(&h) => (Color::Red, Color::LightRed),
+        h if h < 75.0 => (Color::Yellow, Color::LightYellow),
+        h if h < 165.0 => (Color::Green, Color::LightGreen),
+        h if h < 200.0 => (Color::Cyan, Color::LightCyan),
+        h if h < 270.0 => (Color::Blue, Color::LightBlue),
+        _ => (Color::Magenta, Color::LightMagenta),
+    };
+    if bright {light} else {normal}
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::builtin(DEFAULT_THEME).expect("Default theme should be built in")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_themes() {
+        for name in BUILTIN_THEMES {
+            assert!(Theme::named(name, &BTreeMap::new()).is_ok());
+        }
+        assert!(Theme::named("dracula", &BTreeMap::new()).is_err());
+    }
+    #[test]
+    fn detect_support() {
+        let detect = |vars: &[(&str, &str)]| {
+            ColorSupport::from_env(|name| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string()))
+        };
+        assert_eq!(detect(&[("COLORTERM", "truecolor"), ("TERM", "xterm-256color")]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("TERM", "tmux-256color")]), ColorSupport::Indexed256);
+        assert_eq!(detect(&[("TERM", "linux")]), ColorSupport::Ansi16);
+        assert_eq!(detect(&[("TERM", "dumb")]), ColorSupport::NoColor);
+        assert_eq!(detect(&[]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("TERM", "")]), ColorSupport::TrueColor);
+        assert_eq!(detect(&[("NO_COLOR", "1"), ("COLORTERM", "truecolor")]), ColorSupport::NoColor);
+        assert_eq!(detect(&[("NO_COLOR", ""), ("TERM", "linux")]), ColorSupport::Ansi16);
+    }
+    #[test]
+    fn adapt_palette() {
+        let gruvbox = Theme::default();
+        let indexed = gruvbox.adapt(ColorSupport::Indexed256);
+        assert_eq!(indexed.accent, Color::Indexed(172));
+        assert_eq!(indexed.background, Color::Indexed(235));
+        assert_eq!(indexed.text, Color::White);
+
+        let ansi = gruvbox.adapt(ColorSupport::Ansi16);
+        assert_eq!(
+            [ansi.accent, ansi.work, ansi.short_break, ansi.long_break, ansi.alert, ansi.background],
+            [Color::Yellow, Color::Cyan, Color::LightGreen, Color::Magenta, Color::Red, Color::Reset]
+        );
+        assert_eq!(ColorSupport::Ansi16.adapt(Color::Indexed(196)), Color::Red);
+
+        let none = gruvbox.adapt(ColorSupport::NoColor);
+        assert!([none.accent, none.text, none.muted, none.background].iter().all(|c| *c == Color::Reset));
+    }
+    #[test]
+    fn custom_themes() {
+        let mut custom = BTreeMap::new();
+        custom.insert(
+            "dusk".to_string(),
+            ThemeSettings { base: Some("nord".to_string()), accent: Some("#ff8800".to_string()), work: Some("12".to_string()), ..Default::default() },
+        );
+        let dusk = Theme::named("dusk", &custom).unwrap();
+        assert_eq!(dusk.accent, Color::Rgb(255, 136, 0));
+        assert_eq!(dusk.work, Color::Indexed(12));
+        assert_eq!(dusk.background, Theme::builtin("nord").unwrap().background);
+        assert_eq!(Theme::names(&custom).last().map(String::as_str), Some("dusk"));
+
+        custom.get_mut("dusk").unwrap().alert = Some("reddish".to_string());
+        let error = Theme::named("dusk", &custom).unwrap_err().to_string();
+        assert!(error.contains("invalid colour \"reddish\" for alert in theme \"dusk\""));
+        custom.get_mut("dusk").unwrap().base = Some("dusk".to_string());
+        assert!(Theme::named("dusk", &custom).is_err());
+    }
+}
@@ -1,17 +1,29 @@
-use std::{cell::RefCell, fmt:: Display, rc::Rc, time::Duration};
+use std::{cell::RefCell, fmt:: Display, fs, path::{Path, PathBuf}, rc::Rc, time::{Duration, SystemTime}};
+use chrono::{DateTime, Local};
+use serde::*;
+use tokio::time::Instant;
 use tokio_util::sync::CancellationToken;
-use crate::{settings::{self, *}, DEFAULT_BREAK, DEFAULT_ITERATIONS, DEFAULT_WORK};
-#[derive(Debug, PartialEq, Clone, Copy)]
+use crate::error::{Result, StatsError};
+use crate::profile::Phase;
+use crate::stats::{Interruption, InterruptionKind};
+use crate::{settings::*, DEFAULT_BREAK, DEFAULT_ITERATIONS, DEFAULT_LONG_BREAK, DEFAULT_LONG_BREAK_INTERVAL, DEFAULT_WORK};
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum PomodoroState {
     Work(i64),
-    Break(i64)
+    Break(i64),
+    LongBreak(i64),
 }
 
+/// Counts down towards a deadline, so ticks don't drift however late they get handled.
 #[derive(Debug, Clone)]
 struct Countdown {
-    time_left: i64,
+    /// Time until the interval runs out, kept while paused.
+    remaining: Duration,
+    /// Monotonic and wall-clock deadline, set while running.
+    deadline: Option<(Instant, SystemTime)>,
     state: PomodoroState,
-    running: bool,
+    last_sent: i64,
+    wall_clock: fn() -> SystemTime,
 }
 #[derive(Debug, Clone)]
 pub struct Timer{
@@ -23,33 +35,72 @@ pub struct Timer{
     total_elapsed: i64,
     work_state: PomodoroState,
     break_state: PomodoroState,
+    long_break_state: PomodoroState,
+    long_break_interval: u8,
     current_state: PomodoroState,
     next_state: PomodoroState,
     subject: Option<String>,
+    interval_start: Option<DateTime<Local>>,
+    /// Logged during the current work interval.
+    interruptions: Vec<Interruption>,
+    /// Profile being stepped through, `iteration` is the phase number. Empty alternates work and breaks.
+    phases: Vec<Phase>,
+    /// Set once the last interval ran out, until the cycle is started over.
+    completed: bool,
     settings: Rc<RefCell<SettingsTab>>,
 
     pub countdown_command_tx:  Option<tokio::sync::mpsc::Sender<TimerCommand>>,
 }
+/// Live timer state, saved so an interrupted session can be resumed after a restart.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimerSnapshot {
+    pub current_state: PomodoroState,
+    pub iteration: u8,
+    pub total_iterations: u8,
+    pub time_left: i64,
+    pub total_elapsed: i64,
+    pub subject: Option<String>,
+    pub running: bool,
+    pub interval_start: Option<DateTime<Local>>,
+    #[serde(default)]
+    pub interruptions: Vec<Interruption>,
+    pub saved_at: DateTime<Local>,
+}
 pub enum TimerCommand {
     Start,
     NextIteration(PomodoroState),
     Stop,
     Restart(PomodoroState),
+    Restore(PomodoroState, i64),
+    /// Adds seconds to the running interval.
+    Extend(i64),
+    /// Starts the interval over with the given state.
+    Rewind(PomodoroState),
 }
 impl Timer {
     pub fn get_duration(pomodoro_state: &PomodoroState) -> i64 {
         match pomodoro_state {
-                PomodoroState::Work(dur) | PomodoroState::Break(dur) => {return *dur}
-            };
+                PomodoroState::Work(dur) | PomodoroState::Break(dur) | PomodoroState::LongBreak(dur) => *dur
+            }
     }
     pub fn set_total_time(&mut self) {
+        if let Some(phase) = self.phase_state(self.iteration) {
+            let extension = (Timer::get_duration(&self.current_state) - Timer::get_duration(&phase)).max(0);
+            self.total_time = self.phases.iter().map(|phase| phase.duration).sum::<i64>() + extension;
+            return;
+        }
         let duration = Timer::get_duration(&self.work_state);
-        self.total_time = duration * self.total_iterations as i64;
+        // an extended work interval makes the cycle longer
+        let extension = match self.current_state {
+            PomodoroState::Work(current) => (current - duration).max(0),
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_) => 0,
+        };
+        self.total_time = duration * self.total_iterations as i64 + extension;
     }
 
      pub async fn run(&mut self, sender: tokio::sync::mpsc::Sender<i64>,command_rx: tokio::sync::mpsc::Receiver<TimerCommand>, close: CancellationToken) {
 
-        let mut countdown = Countdown::from(self.settings.borrow().timer_settings.clone());
+        let mut countdown = Countdown::new(self.current_state);
         tokio::task::spawn({
             async move {
                 countdown.run(sender, command_rx, close).await;
@@ -58,6 +109,9 @@ impl Timer {
      }
      pub async fn start(&mut self) {
          self.countdown_running = true;
+         if self.interval_start.is_none() {
+             self.interval_start = Some(Local::now());
+         }
          self.send_countdown_commands(TimerCommand::Start).await;
      }
      pub async fn stop(&mut self) {
@@ -65,29 +119,69 @@ impl Timer {
          self.send_countdown_commands(TimerCommand::Stop).await;
      }
 
+    /// Whether the work interval of the current iteration is followed by a long break.
+    pub fn long_break_due(&self) -> bool {
+        self.phases.is_empty() && self.long_break_after(self.iteration)
+    }
+    /// State of the profile's phase at `iteration`, counting from 1.
+    fn phase_state(&self, iteration: u8) -> Option<PomodoroState> {
+        self.phases.get(iteration.checked_sub(1)? as usize).map(Phase::state)
+    }
+    /// Where a cycle starts.
+    fn first_state(&self) -> PomodoroState {
+        self.phase_state(1).unwrap_or(self.work_state)
+    }
+    /// Moves on to the next phase of the profile, if there is one.
+    fn next_phase(&mut self) {
+        let Some(state) = self.phase_state(self.iteration.saturating_add(1)) else {return};
+        self.iteration += 1;
+        self.current_state = state;
+        self.next_state = self.phase_state(self.iteration.saturating_add(1)).unwrap_or(state);
+        self.time_left = Timer::get_duration(&state);
+        self.interval_start = None;
+        self.interruptions.clear();
+    }
+    fn long_break_after(&self, iteration: u8) -> bool {
+        self.long_break_interval != 0 && iteration.is_multiple_of(self.long_break_interval)
+    }
+    fn break_after(&self, iteration: u8) -> PomodoroState {
+        if self.long_break_after(iteration) {
+            self.long_break_state
+        } else {
+            self.break_state
+        }
+    }
     pub fn swap_states(&mut self) {
         if !self.countdown_running {
             match self.current_state {
                 PomodoroState::Work(_) => {
-                    self.current_state = self.break_state;
+                    self.current_state = self.break_after(self.iteration);
                     self.next_state = self.work_state;
                 },
-                PomodoroState::Break(_) => {
+                PomodoroState::Break(_) | PomodoroState::LongBreak(_) => {
                     self.current_state = self.work_state;
-                    self.next_state = self.break_state;
+                    self.next_state = self.break_after(self.iteration + 1);
                 },
             }
             let duration = Timer::get_duration(&self.current_state);
             self.time_left = duration;
+            self.interval_start = None;
+            self.interruptions.clear();
         }
 
     }
     pub async fn next_iteration(&mut self) {
         self.countdown_running = false;
-        self.swap_states();
-        if let PomodoroState::Work(_) = self.current_state {
-            self.iteration += 1;
+        if self.phases.is_empty() {
+            self.swap_states();
+            if let PomodoroState::Work(_) = self.current_state {
+                self.iteration += 1;
+            }
+        }
+        else {
+            self.next_phase();
         }
+        self.set_total_time();
         self.stop().await;
         self.send_countdown_commands(TimerCommand::NextIteration(self.current_state)).await;
         if !self.settings.borrow().ui_settings.pause_after_state_change {
@@ -101,14 +195,127 @@ impl Timer {
     }
 
     pub async fn restart(&mut self) {
-        self.current_state = self.work_state;
-        self.next_state = self.break_state;
+        self.reset();
+        self.send_countdown_commands(TimerCommand::Restart(self.current_state)).await;
+    }
+    /// Back to the start of the cycle, without telling the countdown.
+    fn reset(&mut self) {
+        self.current_state = self.first_state();
         self.iteration = 1;
-        self.time_left = Timer::get_duration(&self.work_state);
+        self.next_state = match self.phases.is_empty() {
+            true => self.break_after(1),
+            false => self.phase_state(2).unwrap_or(self.current_state),
+        };
+        self.time_left = Timer::get_duration(&self.current_state);
         self.total_elapsed = 0;
+        self.interval_start = None;
+        self.interruptions.clear();
         self.countdown_running =false;
+        self.completed = false;
+        self.set_total_time();
+    }
+    /// Adds `seconds` to the current interval, e.g. to snooze a break or keep focusing.
+    pub async fn extend(&mut self, seconds: i64) {
+        self.current_state = match self.current_state {
+            PomodoroState::Work(duration) => PomodoroState::Work(duration + seconds),
+            PomodoroState::Break(duration) => PomodoroState::Break(duration + seconds),
+            PomodoroState::LongBreak(duration) => PomodoroState::LongBreak(duration + seconds),
+        };
+        self.time_left += seconds;
+        self.set_total_time();
+        self.send_countdown_commands(TimerCommand::Extend(seconds)).await;
+    }
+    /// Starts the current interval over, dropping any extension.
+    pub async fn rewind(&mut self) {
+        self.current_state = match self.current_state {
+            _ if !self.phases.is_empty() => self.phase_state(self.iteration).unwrap_or(self.current_state),
+            PomodoroState::Work(_) => self.work_state,
+            PomodoroState::Break(_) => self.break_state,
+            PomodoroState::LongBreak(_) => self.long_break_state,
+        };
+        self.time_left = Timer::get_duration(&self.current_state);
+        self.interval_start = self.countdown_running.then(Local::now);
+        self.set_total_time();
+        self.send_countdown_commands(TimerCommand::Rewind(self.current_state)).await;
+    }
+    /// Logs an interruption, only while a work interval is running.
+    pub fn interrupt(&mut self, kind: InterruptionKind, at: DateTime<Local>) -> bool {
+        let working = matches!(self.current_state, PomodoroState::Work(_)) && self.countdown_running;
+        if working {
+            self.interruptions.push(Interruption { kind, at, note: None });
+        }
+        working
+    }
+    /// Attaches a note to the last interruption of the current interval.
+    pub fn note_interruption(&mut self, note: &str) {
+        let note = note.trim();
+        if let Some(interruption) = self.interruptions.last_mut().filter(|_| !note.is_empty()) {
+            interruption.note = Some(note.to_string());
+        }
+    }
+    pub fn get_interruptions(&self) -> &[Interruption] {
+        &self.interruptions
+    }
+    /// Nothing worth resuming before the first start or after the cycle is over.
+    pub fn in_progress(&self) -> bool {
+        let untouched = self.iteration == 1
+            && self.current_state == self.first_state()
+            && self.time_left == Timer::get_duration(&self.first_state());
+        !untouched && !self.completed && self.time_left >= 0
+    }
+    pub fn is_completed(&self) -> bool {
+        self.completed
+    }
+    pub fn set_completed(&mut self, completed: bool) {
+        self.completed = completed;
+    }
+    pub fn snapshot(&self, saved_at: DateTime<Local>) -> TimerSnapshot {
+        TimerSnapshot {
+            current_state: self.current_state,
+            iteration: self.iteration,
+            total_iterations: self.total_iterations,
+            time_left: self.time_left,
+            total_elapsed: self.total_elapsed,
+            subject: self.subject.clone(),
+            running: self.countdown_running,
+            interval_start: self.interval_start,
+            interruptions: self.interruptions.clone(),
+            saved_at,
+        }
+    }
+    /// Puts the timer back where the snapshot left it. If it was running, the time that passed
+    /// since is taken off the interval, which may run out right away but never skips past it.
+    pub async fn restore(&mut self, snapshot: &TimerSnapshot, now: DateTime<Local>) {
+        let passed = match snapshot.running {
+            true => (now - snapshot.saved_at).num_seconds().max(0),
+            false => 0,
+        };
+        self.current_state = snapshot.current_state;
+        self.iteration = snapshot.iteration;
+        if self.phases.is_empty() {
+            self.total_iterations = snapshot.total_iterations;
+        }
+        else {
+            // the profile may have lost phases since
+            self.iteration = self.iteration.clamp(1, self.get_total_iterations());
+        }
         self.set_total_time();
-        self.send_countdown_commands(TimerCommand::Restart(self.work_state)).await;
+        self.next_state = match self.current_state {
+            _ if !self.phases.is_empty() => self.phase_state(self.iteration + 1).unwrap_or(self.current_state),
+            PomodoroState::Work(_) => self.break_after(self.iteration),
+            PomodoroState::Break(_) | PomodoroState::LongBreak(_) => self.work_state,
+        };
+        self.time_left = (snapshot.time_left - passed).max(0);
+        self.total_elapsed = snapshot.total_elapsed;
+        self.subject = snapshot.subject.clone();
+        self.interval_start = snapshot.interval_start;
+        self.interruptions = snapshot.interruptions.clone();
+        self.countdown_running = false;
+        self.completed = false;
+        self.send_countdown_commands(TimerCommand::Restore(self.current_state, self.time_left)).await;
+        if snapshot.running {
+            self.start().await;
+        }
     }
     pub async fn set_setting(&mut self, setting: PomodoroSettings) -> Option<()> {
         if self.get_running() {return None};
@@ -116,6 +323,8 @@ impl Timer {
             PomodoroSettings::Iterations(iterations) => self.set_total_iterations(iterations.unwrap()),
             PomodoroSettings::WorkTime(_) =>self.set_work_state(PomodoroState::from(setting)),
             PomodoroSettings::BreakTime(_) => self.set_break_state(PomodoroState::from(setting)),
+            PomodoroSettings::LongBreakTime(_) => self.set_long_break_state(PomodoroState::from(setting)),
+            PomodoroSettings::LongBreakInterval(interval) => self.set_long_break_interval(interval.unwrap()),
         }
         self.restart().await;
         Some(())
@@ -134,12 +343,57 @@ impl Timer {
         self.break_state
     }
 
+    pub fn get_long_break_state(&self) -> PomodoroState {
+        self.long_break_state
+    }
+    pub fn get_long_break_interval(&self) -> u8 {
+        self.long_break_interval
+    }
+
     pub fn get_running(&self) -> bool {
          self.countdown_running
     }
+    /// Iterations of the classic timer, or phases of the profile.
     pub fn get_total_iterations(&self) -> u8 {
+        match self.phases.is_empty() {
+            true => self.total_iterations,
+            false => self.phases.len() as u8,
+        }
+    }
+    /// Iterations of the classic timer, kept while a profile is in use.
+    pub fn get_iterations_setting(&self) -> u8 {
         self.total_iterations
     }
+    pub fn get_phases(&self) -> &[Phase] {
+        &self.phases
+    }
+    /// Switches to stepping through `phases`, or back to work and breaks if empty, from the start
+    /// of the cycle.
+    pub fn set_phases(&mut self, phases: Vec<Phase>) {
+        self.phases = phases;
+        self.reset();
+    }
+    /// The profile's current phase, `None` for the classic timer.
+    pub fn get_phase(&self) -> Option<&Phase> {
+        self.get_phase_at(self.iteration)
+    }
+    /// Phase number `iteration`, counting from 1.
+    pub fn get_phase_at(&self, iteration: u8) -> Option<&Phase> {
+        self.phases.get((iteration as usize).checked_sub(1)?)
+    }
+    /// Phase label, or the state for the classic timer.
+    pub fn get_state_label(&self) -> String {
+        match self.get_phase() {
+            Some(phase) => phase.label.clone(),
+            None => self.current_state.to_string(),
+        }
+    }
+    /// Time into a profile's cycle with `time_left` left of the phase, `None` for the classic timer.
+    pub fn profile_elapsed(&self, time_left: i64) -> Option<i64> {
+        if self.phases.is_empty() {return None}
+        let done = self.phases.get(..(self.iteration as usize).saturating_sub(1))?;
+        Some(done.iter().map(|phase| phase.duration).sum::<i64>() + Timer::get_duration(&self.current_state) - time_left)
+    }
     pub fn get_iteration(&self) -> u8 {
         self.iteration
     }
@@ -155,6 +409,24 @@ impl Timer {
     pub fn get_subject(&self) -> Option<String> {
         self.subject.clone()
     }
+    pub fn set_subject(&mut self, subject: Option<String>) {
+        self.subject = subject;
+    }
+    /// Moves to the next subject from the settings, going back to no subject after the last one.
+    pub fn next_subject(&mut self) {
+        let subjects = self.settings.borrow().stats_setting.subjects.clone();
+        let next = match &self.subject {
+            None => subjects.first(),
+            Some(current) => subjects
+                .iter()
+                .position(|s| s == current)
+                .and_then(|i| subjects.get(i + 1)),
+        };
+        self.subject = next.cloned();
+    }
+    pub fn get_interval_start(&self) -> Option<DateTime<Local>> {
+        self.interval_start
+    }
     pub fn set_running(&mut self, state: bool) {
         self.countdown_running = state;
     }
@@ -170,6 +442,12 @@ impl Timer {
     pub fn set_break_state(&mut self, break_state: PomodoroState) {
         self.break_state = break_state;
     }
+    pub fn set_long_break_state(&mut self, long_break_state: PomodoroState) {
+        self.long_break_state = long_break_state;
+    }
+    pub fn set_long_break_interval(&mut self, long_break_interval: u8) {
+        self.long_break_interval = long_break_interval;
+    }
     pub fn set_total_iterations(&mut self, total_iterations: u8) {
         self.total_iterations = total_iterations;
     }
@@ -180,42 +458,122 @@ impl Timer {
 impl Countdown {
      pub async fn run(&mut self, sender: tokio::sync::mpsc::Sender<i64>,mut command_rx: tokio::sync::mpsc::Receiver<TimerCommand>, close: CancellationToken) {
          loop {
+             let next_tick = self.next_tick();
              tokio::select! {
-             Some(command) = command_rx.recv() => {
+             command = command_rx.recv() => {
+                 let Some(command) = command else {break};
                  match command {
-                     TimerCommand::Start => {self.running = true},
-                     TimerCommand::Stop => {self.running = false},
-                     TimerCommand::NextIteration(state) => {self.next_iteration(state)},
+                     TimerCommand::Start => self.start(),
+                     TimerCommand::Stop => self.stop(),
+                     TimerCommand::NextIteration(state) | TimerCommand::Rewind(state) => {self.next_iteration(state)},
+                     TimerCommand::Extend(seconds) => self.extend(Duration::from_secs(seconds.max(0) as u64)),
                      TimerCommand::Restart(state) => self.restart(state),
+                     TimerCommand::Restore(state, time_left) => {
+                         self.state = state;
+                         self.set_time_left(time_left);
+                     },
                  };
              }
-             _ = tokio::time::sleep(Duration::from_secs(1)), if self.running && self.time_left >= 0 => {
-                 self.time_left -= 1;
-                 sender.send(self.time_left).await.unwrap();
+             _ = tokio::time::sleep_until(next_tick.unwrap_or_else(Instant::now)), if next_tick.is_some() => {
+                 let time_left = self.time_left();
+                 if time_left < self.last_sent {
+                     self.last_sent = time_left;
+                     if sender.send(time_left).await.is_err() {break}
+                 }
              }
              _ = close.cancelled() => {break}
              }
          }
      }
+    /// Seconds left as shown on the clock. Reaches -1 once the interval has run out.
+    fn time_left(&self) -> i64 {
+        let remaining = match self.deadline {
+            Some((deadline, wall_deadline)) => {
+                let monotonic = deadline.saturating_duration_since(Instant::now());
+                // the monotonic clock stands still while the machine is suspended, the wall clock doesn't
+                let wall = wall_deadline.duration_since((self.wall_clock)()).unwrap_or(Duration::ZERO);
+                monotonic.min(wall)
+            }
+            None => self.remaining,
+        };
+        (remaining.as_millis().div_ceil(1000) as i64 - 1).max(-1)
+    }
+    /// When the shown second changes next, `None` if paused or already finished.
+    fn next_tick(&self) -> Option<Instant> {
+        let (deadline, _) = self.deadline?;
+        let time_left = self.time_left();
+        if self.last_sent < 0 {return None}
+        if time_left < self.last_sent {return Some(Instant::now())}
+        Some(deadline - Duration::from_secs(time_left as u64))
+    }
+    fn set_time_left(&mut self, time_left: i64) {
+        // runs out one second after showing zero, same as the clock shows it
+        self.remaining = Duration::from_secs((time_left + 1).max(0) as u64);
+        self.last_sent = time_left;
+        if self.deadline.is_some() {
+            self.deadline = None;
+            self.start();
+        }
+    }
+    fn extend(&mut self, by: Duration) {
+        match &mut self.deadline {
+            Some((deadline, wall_deadline)) => {
+                *deadline += by;
+                *wall_deadline += by;
+            }
+            None => self.remaining += by,
+        }
+        self.last_sent = self.time_left();
+    }
+    fn start(&mut self) {
+        if self.deadline.is_none() {
+            self.deadline = Some((Instant::now() + self.remaining, (self.wall_clock)() + self.remaining));
+        }
+    }
+    fn stop(&mut self) {
+        if let Some((deadline, _)) = self.deadline.take() {
+            self.remaining = deadline.saturating_duration_since(Instant::now());
+        }
+    }
     pub fn next_iteration(&mut self, state: PomodoroState) {
         self.state = state;
-        self.time_left = Timer::get_duration(&state);
+        self.set_time_left(Timer::get_duration(&state));
     }
 
     pub fn restart(&mut self, state: PomodoroState) {
-        self.time_left = Timer::get_duration(&state);
+        self.deadline = None;
+        self.set_time_left(Timer::get_duration(&state));
         self.state = state;
-        self.running = false;
     }
 }
 
-// traits
-impl Into<PomodoroSettings> for &PomodoroState {
-    fn into(self) -> PomodoroSettings {
-        match self {
-            PomodoroState::Break(time) => { PomodoroSettings::BreakTime(Some(*time)) },
-            PomodoroState::Work(time) => PomodoroSettings::BreakTime(Some(*time)) }
+impl TimerSnapshot {
+    pub fn default_path() -> Result<PathBuf> {
+        Ok(TimerSnapshot::path_for(&SettingsTab::config_path()?))
+    }
+    /// Where the snapshot goes for the config at `config_path`.
+    pub fn path_for(config_path: &Path) -> PathBuf {
+        config_path.with_file_name("timer_state.json")
+    }
+    pub fn load(path: impl AsRef<Path>) -> Option<TimerSnapshot> {
+        let data = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&data).ok()
+    }
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent() {
+            if !dir.exists() {fs::create_dir_all(dir)?}
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| StatsError::SaveError(e.to_string()))?;
+        fs::write(path, json)?;
+        Ok(())
+    }
+}
 
+// traits
+impl From<&PomodoroState> for PomodoroSettings {
+    fn from(value: &PomodoroState) -> Self {
+        PomodoroSettings::from(*value)
     }
 }
 impl From<PomodoroSettings> for PomodoroState {
@@ -223,6 +581,7 @@ impl From<PomodoroSettings> for PomodoroState {
         match value {
             PomodoroSettings::WorkTime(Some(time)) => PomodoroState::Work(time),
             PomodoroSettings::BreakTime(Some(time)) => PomodoroState::Break(time),
+            PomodoroSettings::LongBreakTime(Some(time)) => PomodoroState::LongBreak(time),
             _ => PomodoroState::Break(-1),
         }
 
@@ -238,6 +597,9 @@ impl Display for PomodoroState {
             PomodoroState::Break(_) => {
                 write!(f, "Break")
             }
+            PomodoroState::LongBreak(_) => {
+                write!(f, "Long Break")
+            }
         }
     }
 }
@@ -245,17 +607,32 @@ impl Default for Timer {
     fn default() -> Self {
         let work_state = PomodoroState::Work(DEFAULT_WORK);
         let break_state = PomodoroState::Break(DEFAULT_BREAK);
+        let long_break_state = PomodoroState::LongBreak(DEFAULT_LONG_BREAK);
+        let long_break_interval = DEFAULT_LONG_BREAK_INTERVAL;
         let total_iterations = DEFAULT_ITERATIONS;
         let duration = Timer::get_duration(&work_state);
         let total_time: i64 = duration * total_iterations as i64;
         let settings = Rc::new(RefCell::new(SettingsTab::default()));
-        Timer {time_left: duration,countdown_running: false, countdown_command_tx: None, total_iterations, current_state: work_state, next_state:break_state, iteration: 1, total_time, total_elapsed: 0, work_state, break_state, settings, subject:None}
+        Timer {time_left: duration,countdown_running: false, countdown_command_tx: None, total_iterations, current_state: work_state, next_state:break_state, iteration: 1, total_time, total_elapsed: 0, work_state, break_state, long_break_state, long_break_interval, settings, subject:None, interval_start: None, interruptions: Vec::new(), phases: Vec::new(), completed: false}
         
     }
 }
+impl Countdown {
+    fn new(state: PomodoroState) -> Self {
+        let mut countdown = Countdown {
+            remaining: Duration::ZERO,
+            deadline: None,
+            state,
+            last_sent: 0,
+            wall_clock: SystemTime::now,
+        };
+        countdown.set_time_left(Timer::get_duration(&state));
+        countdown
+    }
+}
 impl Default for Countdown {
     fn default() -> Self {
-        Countdown {running: false, time_left: DEFAULT_WORK, state: PomodoroState::Work(DEFAULT_WORK)}
+        Countdown::new(PomodoroState::Work(DEFAULT_WORK))
     }
 }
 impl From<TimerSettings> for Timer {
@@ -263,9 +640,11 @@ impl From<TimerSettings> for Timer {
         let mut timer = Timer::default();
         timer.work_state = PomodoroState::Work(value.work_time);
         timer.break_state = PomodoroState::Break(value.break_time);
+        timer.long_break_state = PomodoroState::LongBreak(value.long_break_time);
+        timer.long_break_interval = value.long_break_interval;
         timer.total_iterations = value.iterations;
         timer.current_state = timer.work_state;
-        timer.next_state = timer.break_state;
+        timer.next_state = timer.break_after(1);
         timer.time_left = Timer::get_duration(&timer.current_state);
         timer.total_elapsed = 0;
         timer.set_total_time();
@@ -275,15 +654,228 @@ impl From<TimerSettings> for Timer {
 
 impl From<TimerSettings> for Countdown {
     fn from(value: TimerSettings) -> Self {
-        let state = PomodoroState::Work(value.work_time);
-        let running = false;
-        let time_left = Timer::get_duration(&state);
-        Countdown { time_left, state, running }
+        Countdown::new(PomodoroState::Work(value.work_time))
     }
 }
 #[cfg(test)]
 mod tests {
+    use std::cell::Cell;
 
+    use super::*;
 
+    thread_local! {
+        static WALL_OFFSET: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+    }
+    fn fake_wall_clock() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000) + WALL_OFFSET.with(Cell::get)
+    }
+
+    fn spawn_countdown(countdown: Countdown) -> (tokio::sync::mpsc::Sender<TimerCommand>, tokio::sync::mpsc::Receiver<i64>) {
+        let (tx, rx) = tokio::sync::mpsc::channel(8);
+        let (command_tx, command_rx) = tokio::sync::mpsc::channel(8);
+        let mut countdown = countdown;
+        tokio::spawn(async move { countdown.run(tx, command_rx, CancellationToken::new()).await });
+        (command_tx, rx)
+    }
+    async fn ticks_until_done(rx: &mut tokio::sync::mpsc::Receiver<i64>) -> Vec<i64> {
+        let mut ticks = Vec::new();
+        while let Some(time) = rx.recv().await {
+            ticks.push(time);
+            if time == -1 {break}
+        }
+        ticks
+    }
 
+    #[tokio::test(start_paused = true)]
+    async fn countdown_ticks_every_second() {
+        let (commands, mut rx) = spawn_countdown(Countdown::new(PomodoroState::Work(3)));
+        let begin = Instant::now();
+        let _ = commands.send(TimerCommand::Start).await;
+        assert_eq!(ticks_until_done(&mut rx).await, [2, 1, 0, -1]);
+        assert_eq!(begin.elapsed(), Duration::from_secs(4));
+    }
+    #[tokio::test(start_paused = true)]
+    async fn commands_dont_delay_ticks() {
+        let (commands, mut rx) = spawn_countdown(Countdown::new(PomodoroState::Work(5)));
+        let begin = Instant::now();
+        let _ = commands.send(TimerCommand::Start).await;
+        // every command used to restart the one second sleep
+        tokio::spawn(async move {
+            for _ in 0..20 {
+                tokio::time::sleep(Duration::from_millis(400)).await;
+                let _ = commands.send(TimerCommand::Start).await;
+            }
+        });
+        assert_eq!(ticks_until_done(&mut rx).await, [4, 3, 2, 1, 0, -1]);
+        assert_eq!(begin.elapsed(), Duration::from_secs(6));
+    }
+    #[tokio::test(start_paused = true)]
+    async fn pause_keeps_partial_second() {
+        let (commands, mut rx) = spawn_countdown(Countdown::new(PomodoroState::Work(3)));
+        let _ = commands.send(TimerCommand::Start).await;
+        assert_eq!(rx.recv().await, Some(2));
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let _ = commands.send(TimerCommand::Stop).await;
+        tokio::time::sleep(Duration::from_secs(60)).await;
+
+        let resumed = Instant::now();
+        let _ = commands.send(TimerCommand::Start).await;
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(resumed.elapsed(), Duration::from_millis(500));
+        assert_eq!(ticks_until_done(&mut rx).await, [0, -1]);
+        assert_eq!(resumed.elapsed(), Duration::from_millis(2500));
+    }
+    #[tokio::test(start_paused = true)]
+    async fn extend_and_rewind() {
+        let (commands, mut rx) = spawn_countdown(Countdown::new(PomodoroState::Break(3)));
+        let begin = Instant::now();
+        let _ = commands.send(TimerCommand::Start).await;
+        assert_eq!(rx.recv().await, Some(2));
+        let _ = commands.send(TimerCommand::Extend(5)).await;
+        assert_eq!(rx.recv().await, Some(6));
+        assert_eq!(begin.elapsed(), Duration::from_secs(2));
+
+        let _ = commands.send(TimerCommand::Rewind(PomodoroState::Break(3))).await;
+        assert_eq!(ticks_until_done(&mut rx).await, [2, 1, 0, -1]);
+        assert_eq!(begin.elapsed(), Duration::from_secs(6));
+    }
+    #[tokio::test]
+    async fn interruptions_only_while_working() {
+        let mut timer = Timer::default();
+        let now = Local::now();
+        assert!(!timer.interrupt(InterruptionKind::Internal, now));
+        timer.start().await;
+        assert!(timer.interrupt(InterruptionKind::Internal, now));
+        assert!(timer.interrupt(InterruptionKind::External, now));
+        timer.note_interruption("  phone ");
+        timer.note_interruption("");
+        assert_eq!(timer.get_interruptions()[1].note.as_deref(), Some("phone"));
+        assert_eq!(timer.snapshot(now).interruptions.len(), 2);
+
+        // a new interval starts with a clean slate, and breaks can't be interrupted
+        timer.next_iteration().await;
+        assert!(timer.get_interruptions().is_empty());
+        assert!(timer.get_running());
+        assert!(!timer.interrupt(InterruptionKind::External, now));
+    }
+    #[tokio::test]
+    async fn extend_updates_totals() {
+        let mut timer = Timer::default();
+        let work = Timer::get_duration(&timer.get_work_state());
+        let total = timer.get_total_time();
+        timer.extend(300).await;
+        assert_eq!(timer.get_current_state(), PomodoroState::Work(work + 300));
+        assert_eq!(timer.get_timeleft(), work + 300);
+        assert_eq!(timer.get_total_time(), total + 300);
+        assert!(timer.in_progress());
+
+        timer.rewind().await;
+        assert_eq!(timer.get_current_state(), timer.get_work_state());
+        assert_eq!(timer.get_total_time(), total);
+        assert!(!timer.in_progress());
+    }
+    #[tokio::test(start_paused = true)]
+    async fn suspend_is_caught_up() {
+        let mut countdown = Countdown::new(PomodoroState::Work(600));
+        countdown.wall_clock = fake_wall_clock;
+        let (commands, mut rx) = spawn_countdown(countdown);
+        let _ = commands.send(TimerCommand::Start).await;
+        assert_eq!(rx.recv().await, Some(599));
+
+        // the monotonic clock stands still during suspend, only the wall clock moves
+        WALL_OFFSET.with(|offset| offset.set(Duration::from_secs(300)));
+        assert_eq!(rx.recv().await, Some(300));
+        WALL_OFFSET.with(|offset| offset.set(Duration::from_secs(3600)));
+        assert_eq!(rx.recv().await, Some(-1));
+    }
+
+    #[test]
+    fn next_subject_cycles() {
+        let mut timer = Timer::default();
+        timer.settings.borrow_mut().stats_setting.subjects = vec!["rust".to_string(), "maths".to_string()];
+        timer.next_subject();
+        assert_eq!(timer.get_subject(), Some("rust".to_string()));
+        timer.next_subject();
+        assert_eq!(timer.get_subject(), Some("maths".to_string()));
+        timer.next_subject();
+        assert_eq!(timer.get_subject(), None);
+    }
+    #[tokio::test]
+    async fn snapshot_restore() {
+        let mut timer = Timer::default();
+        timer.swap_states();
+        timer.set_time_left(200);
+        timer.set_subject(Some("rust".to_string()));
+        timer.set_running(true);
+        let saved_at = Local::now();
+        let snapshot = timer.snapshot(saved_at);
+        assert!(timer.in_progress());
+
+        let mut restored = Timer::default();
+        restored.restore(&snapshot, saved_at + chrono::Duration::seconds(50)).await;
+        assert_eq!(restored.get_current_state(), PomodoroState::Break(DEFAULT_BREAK));
+        assert_eq!(restored.get_timeleft(), 150);
+        assert_eq!(restored.get_subject(), Some("rust".to_string()));
+        assert!(restored.get_running());
+
+        // a paused timer doesn't lose time, a running one never goes below zero
+        let paused = TimerSnapshot {running: false, ..snapshot.clone()};
+        restored.restore(&paused, saved_at + chrono::Duration::hours(5)).await;
+        assert_eq!(restored.get_timeleft(), 200);
+        restored.restore(&snapshot, saved_at + chrono::Duration::hours(5)).await;
+        assert_eq!(restored.get_timeleft(), 0);
+        assert!(!Timer::default().in_progress());
+    }
+    #[test]
+    fn long_break_every_interval() {
+        let mut timer = Timer::from(TimerSettings {long_break_interval: 2, ..TimerSettings::default()});
+        let mut states = Vec::new();
+        for _ in 0..6 {
+            timer.swap_states();
+            if let PomodoroState::Work(_) = timer.get_current_state() {
+                timer.iteration += 1;
+            }
+            states.push(timer.get_current_state().to_string());
+        }
+        assert_eq!(states, ["Break", "Work", "Long Break", "Work", "Break", "Work"]);
+        assert_eq!(timer.get_next_state(), PomodoroState::LongBreak(DEFAULT_LONG_BREAK));
+    }
+    #[tokio::test]
+    async fn profile_steps_through_phases() {
+        use crate::profile::PhaseKind;
+        let mut timer = Timer::default();
+        timer.set_phases(vec![
+            Phase::new("Warm-up", 300, PhaseKind::Work),
+            Phase::new("Deep work", 3000, PhaseKind::Work),
+            Phase::new("Break", 600, PhaseKind::Break),
+        ]);
+        assert_eq!((timer.get_state_label(), timer.get_total_iterations()), ("Warm-up".to_string(), 3));
+        assert_eq!((timer.get_timeleft(), timer.get_total_time()), (300, 3900));
+        assert!(!timer.in_progress());
+
+        timer.next_iteration().await;
+        assert_eq!(timer.get_current_state(), PomodoroState::Work(3000));
+        assert_eq!(timer.get_next_state(), PomodoroState::Break(600));
+        assert_eq!(timer.profile_elapsed(1000), Some(2300));
+        assert!(!timer.long_break_due());
+        timer.extend(60).await;
+        timer.rewind().await;
+        assert_eq!(timer.get_current_state(), PomodoroState::Work(3000));
+        timer.next_iteration().await;
+        timer.next_iteration().await;
+        // there's no phase after the last one
+        assert_eq!((timer.get_state_label(), timer.get_iteration()), ("Break".to_string(), 3));
+
+        timer.restart().await;
+        assert_eq!(timer.get_state_label(), "Warm-up");
+        timer.set_phases(Vec::new());
+        assert_eq!((timer.get_state_label(), timer.get_total_iterations()), ("Work".to_string(), DEFAULT_ITERATIONS));
+        assert_eq!(timer.get_timeleft(), DEFAULT_WORK);
+    }
+
+    #[test]
+    fn state_converts_to_matching_setting() {
+        assert_eq!(PomodoroSettings::from(&PomodoroState::Work(5)), PomodoroSettings::WorkTime(Some(5)));
+        assert_eq!(PomodoroSettings::from(&PomodoroState::Break(3)), PomodoroSettings::BreakTime(Some(3)));
+    }
 }
@@ -1,46 +1,136 @@
 use crate::app::App;
+use crate::keymap::{Action, Keymap};
 use crate::romodoro::Pomodoro;
 use crate::settings::SettingsTab;
+use crate::stats::{InterruptionKind, StatsRange};
+use crate::profile::Phase;
+use crate::theme::{ColorSupport, Theme};
+use crate::font::Font;
+use chrono::Local;
 use ratatui::{
     self,
     buffer::Buffer,
-    layout::{Alignment, Constraint, Direction, Layout, Rect},
+    layout::{Alignment, Constraint, Direction, Flex, Layout, Rect},
     style::{Color, Modifier, Style, Stylize},
-    text::Span,
-    widgets::{Block, BorderType, Borders, Clear, Gauge, Paragraph, Tabs, Widget},
+    text::{Line, Span},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, BorderType, Borders, Cell, Clear, Gauge, LineGauge, Paragraph, Row,
+        StatefulWidget, Table, TableState, Tabs, Widget,
+    },
     Frame,
 };
-// gruvbox theme
-const YELLOW: Color = Color::Rgb(215, 153, 33);
-const BLUE: Color = Color::Rgb(69, 133, 136);
-const GREEN: Color = Color::Rgb(142, 192, 124);
-const RED: Color = Color::Rgb(204, 36, 29);
-const BG: Color = Color::Rgb(40, 40, 40);
+
+/// Below this the tabs collapse to one line.
+const FULL_WIDTH: u16 = 60;
+const FULL_HEIGHT: u16 = 24;
+/// Below this only the status line is drawn.
+const STATUS_LINE_WIDTH: u16 = 24;
+const STATUS_LINE_HEIGHT: u16 = 5;
+/// Space the timer needs for the big clock, gap and gauge.
+const FULL_TIMER_WIDTH: u16 = 40;
+const FULL_TIMER_HEIGHT: u16 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum LayoutMode {
+    Full,
+    /// One line of tabs, the timer shows a one-line clock.
+    Compact,
+    /// A single line with the state, clock and iteration.
+    StatusLine,
+}
+impl LayoutMode {
+    fn for_area(area: Rect) -> LayoutMode {
+        if area.width < STATUS_LINE_WIDTH || area.height < STATUS_LINE_HEIGHT {
+            LayoutMode::StatusLine
+        } else if area.width < FULL_WIDTH || area.height < FULL_HEIGHT {
+            LayoutMode::Compact
+        } else {
+            LayoutMode::Full
+        }
+    }
+}
+
+type FooterItems = &'static [(&'static [Action], &'static str)];
+const TIMER_FOOTER: FooterItems = &[
+    (&[Action::ToggleTimer], "Start/Stop"),
+    (&[Action::Skip], "Skip"),
+    (&[Action::Extend], "5 More Minutes"),
+    (&[Action::Rewind], "Rewind"),
+    (&[Action::RestartCycle], "Restart Cycle"),
+    (&[Action::NextSubject], "Switch Subject"),
+    (&[Action::NextTask], "Switch Task"),
+    (&[Action::NextPreset], "Switch Preset"),
+    (&[Action::InternalInterruption, Action::ExternalInterruption], "Interrupted"),
+    (&[Action::NextTab], "Next Tab"),
+    (&[Action::Quit], "Quit"),
+];
+const SETTINGS_FOOTER: FooterItems = &[
+    (&[Action::Up, Action::Down], "Select"),
+    (&[Action::Decrease, Action::Increase], "Adjust Value"),
+    (&[Action::Confirm], "Confirm"),
+    (&[Action::AddSubject, Action::RenameSubject, Action::DeleteSubject], "Add/Rename/Delete Subject"),
+    (&[Action::NextTab], "Next Tab"),
+    (&[Action::RestoreDefaults], "Restore Defaults"),
+    (&[Action::Quit], "Quit"),
+];
+const STATS_FOOTER: FooterItems = &[
+    (&[Action::ScrollBack, Action::ScrollForward], "Scroll Days"),
+    (&[Action::ToggleRange], "7/30 Days"),
+    (&[Action::ExportCsv, Action::ExportJson], "Export CSV/JSON"),
+    (&[Action::NextTab], "Next Tab"),
+    (&[Action::Quit], "Quit"),
+];
+const TASKS_FOOTER: FooterItems = &[
+    (&[Action::TaskUp, Action::TaskDown], "Select"),
+    (&[Action::MoveTaskUp, Action::MoveTaskDown], "Move"),
+    (&[Action::DecreaseEstimate, Action::IncreaseEstimate], "Estimate"),
+    (&[Action::CompleteTask], "Done"),
+    (&[Action::ActivateTask], "Active"),
+    (&[Action::AddTask, Action::RenameTask, Action::DeleteTask], "Add/Rename/Delete"),
+    (&[Action::NextTab], "Next Tab"),
+    (&[Action::Quit], "Quit"),
+];
 
 impl Widget for &App {
     fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
-        let tabs = ["Pomodoro Timer", "Settings", "Stats"];
+        let theme = self.get_settings_ref().borrow().get_theme();
+        let mode = LayoutMode::for_area(area);
+        if mode == LayoutMode::StatusLine {
+            self.render_status_line(area, buf, &theme);
+            return;
+        }
+        let tabs = match mode {
+            LayoutMode::Full => ["Pomodoro Timer", "Settings", "Stats", "Tasks"],
+            _ => ["Timer", "Settings", "Stats", "Tasks"],
+        };
         let tab_titles: Vec<Span> = tabs
             .iter()
-            .map(|t| Span::styled(*t, Style::default().fg(Color::White)))
+            .map(|t| Span::styled(*t, Style::default().fg(theme.text)))
             .collect();
         let selected_tab = self.get_selected_tab();
 
         let tabs_widget = Tabs::new(tab_titles)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .border_type(BorderType::Rounded)
-                    .title(" Menu ")
-                    .border_style(Style::default().fg(YELLOW)),
-            )
-            .highlight_style(Style::default().fg(Color::Rgb(240, 94, 90)))
+            // bold keeps the selected tab visible without colours
+            .highlight_style(Style::default().fg(theme.highlight).add_modifier(Modifier::BOLD))
             .select(selected_tab);
+        let (tabs_height, tabs_widget) = match mode {
+            LayoutMode::Full => (
+                3,
+                tabs_widget.block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .title(" Menu ")
+                        .border_style(Style::default().fg(theme.accent)),
+                ),
+            ),
+            _ => (1, tabs_widget),
+        };
 
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3), // Tab titles
+                Constraint::Length(tabs_height), // Tab titles
                 Constraint::Min(1),    // Main content area
                 Constraint::Max(1),    // footer
             ])
@@ -48,91 +138,297 @@ impl Widget for &App {
 
         let tab_layout = Layout::default()
             .direction(Direction::Horizontal)
-            .constraints([Constraint::Max(38), Constraint::Min(1)])
+            .constraints([Constraint::Max(46), Constraint::Min(1)])
             .split(layout[0]);
 
         tabs_widget.render(tab_layout[0], buf);
 
-        let popup_area = centered_rect(40, 30, area);
-        let popup_block = Block::default()
-            .borders(Borders::NONE)
-            .style(Style::default().bg(BG));
-        let popup_layout = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(30)])
-            .vertical_margin(0)
-            .split(popup_area);
-        let popup_yes_no_layout = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-            .horizontal_margin(0)
-            .split(popup_layout[1]);
-        let yes_paragraph = Paragraph::new("<y>es")
-            .alignment(Alignment::Center)
-            .centered()
-            .style(Style::default().fg(GREEN))
-            .block(
-                Block::default()
-                    .borders(Borders::all())
-                    .border_type(BorderType::Rounded),
-            );
-        let no_paragraph = Paragraph::new("<n>o")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(RED))
-            .block(
-                Block::default()
-                    .borders(Borders::all())
-                    .border_type(BorderType::Rounded),
-            );
-        let question_paragraph = Paragraph::new("This will reset your current timer!")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Gray))
-            .block(
-                Block::default()
-                    .borders(Borders::all())
-                    .border_type(BorderType::Rounded)
-                    .title("You sure?"),
-            );
         match selected_tab {
             0 => self.get_pomodoro_ref().render(layout[1], buf),
             1 => self.get_settings_ref().borrow().render(layout[1], buf),
             2 => self.render_stats(layout[1], buf),
+            3 => self.render_tasks(layout[1], buf),
             _ => {}
         }
-        if self.get_show_popup() && selected_tab == 1 {
-            Clear.render(area, buf);
-            popup_block.render(popup_area, buf);
-            question_paragraph.render(popup_layout[0], buf);
-            yes_paragraph.render(popup_yes_no_layout[0], buf);
-            no_paragraph.render(popup_yes_no_layout[1], buf);
+        if let Some((title, input)) = self.text_input() {
+            let input_area = centered_rect(40, 20, area);
+            Clear.render(input_area, buf);
+            Paragraph::new(format!("{}_", input))
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(theme.text).bg(theme.background))
+                .block(
+                    Block::default()
+                        .borders(Borders::all())
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(theme.accent))
+                        .title(format!(" {title} ")),
+                )
+                .render(input_area, buf);
+        }
+        if self.get_show_popup() {
+            let title = match self.get_pending_preset() {
+                Some(name) => format!("Switch to {name}?"),
+                None => "You sure?".to_string(),
+            };
+            render_confirm_popup(&title, "This will reset your current timer!", self.get_keymap(), &theme, area, buf);
+            return;
+        }
+        if let Some(snapshot) = self.get_resume_offer() {
+            let question = format!(
+                "Resume {} {}/{} with {:02}:{:02} left?",
+                snapshot.current_state,
+                snapshot.iteration,
+                snapshot.total_iterations,
+                snapshot.time_left.max(0) / 60,
+                snapshot.time_left.max(0) % 60
+            );
+            render_confirm_popup("Unfinished session", &question, self.get_keymap(), &theme, area, buf);
             return;
         }
         self.render_footer(layout[2], buf);
     }
 }
 impl App {
+    /// Title and contents of the text popup open on the current tab.
+    fn text_input(&self) -> Option<(&'static str, &str)> {
+        match (self.get_selected_tab(), self.get_subject_input(), self.get_task_input(), self.get_note_input()) {
+            (0, _, _, Some(note)) => Some(match note.kind {
+                InterruptionKind::Internal => ("Internal interruption note", note.text.as_str()),
+                InterruptionKind::External => ("External interruption note", note.text.as_str()),
+            }),
+            (1, Some(input), _, _) if input.editing.is_some() => Some(("Rename subject", &input.text)),
+            (1, Some(input), _, _) => Some(("New subject", &input.text)),
+            (3, _, Some(input), _) if input.editing.is_some() => Some(("Rename task", &input.text)),
+            (3, _, Some(input), _) => Some(("New task", &input.text)),
+            _ => None,
+        }
+    }
+    /// Everything on one line for tiny panes, including pending questions.
+    fn render_status_line(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let keymap = self.get_keymap();
+        let yes_no = format!(
+            "{}/{}",
+            keymap.hint(&[Action::Yes]).unwrap_or_default(),
+            keymap.hint(&[Action::No]).unwrap_or_default()
+        );
+        let line = if let Some(snapshot) = self.get_resume_offer() {
+            Line::from(format!("Resume {} {}/{}? {}", snapshot.current_state, snapshot.iteration, snapshot.total_iterations, yes_no))
+        } else if let Some((title, input)) = self.text_input() {
+            Line::from(format!("{}: {}_", title, input))
+        } else if let Some(name) = self.get_pending_preset() {
+            Line::from(format!("Switch to {} and reset the timer? {}", name, yes_no))
+        } else if self.get_show_popup() {
+            Line::from(format!("Reset the timer? {}", yes_no))
+        } else {
+            self.get_pomodoro_ref().status_line(theme)
+        };
+        let line_area = Rect { y: area.y + area.height / 2, height: area.height.min(1), ..area };
+        Paragraph::new(line.fg(theme.text)).alignment(Alignment::Center).render(line_area, buf);
+    }
     pub fn render_footer(&self, area: Rect, buf: &mut Buffer) {
+        let keymap = self.get_keymap();
+        let theme = self.get_settings_ref().borrow().get_theme();
         let footer_text = match self.get_selected_tab() {
-            0 => "Space: Start/Stop | Tab: Next Tab | Q: Quit",
-            1 => "↑↓: Select | ←→: Adjust Value | Space: Confirm | Tab: Next Tab | r: Restore Defaults | Q: Quit |" ,
-            _ => "Tab: Next Tab | Q: Quit",
+            0 if self.get_note_input().is_some() => "Enter: Save Note | Esc: No Note".to_string(),
+            0 => footer_text(keymap, TIMER_FOOTER),
+            _ if self.text_input().is_some() => "Enter: Save | Esc: Cancel".to_string(),
+            1 => footer_text(keymap, SETTINGS_FOOTER),
+            2 => footer_text(keymap, STATS_FOOTER),
+            _ => footer_text(keymap, TASKS_FOOTER),
         };
 
         let footer = Paragraph::new(footer_text)
             .alignment(Alignment::Center)
             .style(
                 Style::default()
-                    .fg(Color::Gray)
+                    .fg(theme.muted)
                     .add_modifier(Modifier::ITALIC),
             );
 
         footer.render(area, buf);
     }
+    fn render_tasks(&self, area: Rect, buf: &mut Buffer) {
+        let tasks_ref = self.get_pomodoro_ref().get_tasks_ref();
+        let tasks = tasks_ref.borrow();
+        let theme = self.get_settings_ref().borrow().get_theme();
+
+        let outer_block = Block::default()
+            .title(" Tasks ")
+            .title_bottom(Line::from(format!(" {} left ", tasks.remaining())).right_aligned())
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.accent));
+        let inner_area = outer_block.inner(area);
+        outer_block.render(area, buf);
+
+        if tasks.get_tasks().is_empty() {
+            let add_key = self.get_keymap().hint(&[Action::AddTask]).unwrap_or_default();
+            Paragraph::new(format!("No tasks yet, press {add_key} to add one"))
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC))
+                .render(Rect { y: inner_area.y + inner_area.height / 2, height: inner_area.height.min(1), ..inner_area }, buf);
+            fill_background(area, buf, theme.background);
+            return;
+        }
+        let rows: Vec<Row> = tasks
+            .get_tasks()
+            .iter()
+            .enumerate()
+            .map(|(i, task)| {
+                let active = tasks.get_active_index() == Some(i);
+                let style = match (task.done, active) {
+                    (true, _) => Style::default().fg(theme.muted).add_modifier(Modifier::CROSSED_OUT),
+                    (false, true) => Style::default().fg(theme.work).add_modifier(Modifier::BOLD),
+                    (false, false) => Style::default().fg(theme.text),
+                };
+                Row::new([
+                    Cell::from(if active {"▶"} else {""}),
+                    Cell::from(if task.done {"[x]"} else {"[ ]"}),
+                    Cell::from(task.name.as_str()),
+                    Cell::from(Line::from(format!("{}/{}", task.pomodoros, task.estimate)).right_aligned()),
+                ])
+                .style(style)
+            })
+            .collect();
+        let table = Table::new(
+            rows,
+            [Constraint::Length(1), Constraint::Length(3), Constraint::Min(1), Constraint::Length(5)],
+        )
+        .header(Row::new(["", "", "Task", "Done"]).style(Style::default().fg(theme.muted)))
+        .row_highlight_style(Style::default().fg(theme.accent).add_modifier(Modifier::BOLD | Modifier::REVERSED))
+        .highlight_symbol("› ");
+        let mut state = TableState::default().with_selected(tasks.get_selected());
+        StatefulWidget::render(table, inner_area.inner(ratatui::layout::Margin::new(1, 0)), buf, &mut state);
+        fill_background(area, buf, theme.background);
+    }
     fn render_stats(&self, area: Rect, buf: &mut Buffer) {
-        let text = Paragraph::new("Stats Page (WIP)".to_string())
+        let stats_ref = self.get_pomodoro_ref().get_stats_ref();
+        let stats = stats_ref.borrow();
+        let stats_tab = self.get_stats_tab();
+        let theme = self.get_settings_ref().borrow().get_theme();
+        let today = Local::now().date_naive();
+        let (from, to) = stats_tab.get_window(today);
+        let (current_streak, longest_streak) = stats.streaks(today);
+        let (internal, external) = stats.interruptions(from, to);
+
+        let mut outer_block = Block::default()
+            .title(" Stats ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.accent));
+        if let Some(message) = &stats_tab.message {
+            outer_block = outer_block.title_bottom(Line::from(format!(" {message} ")).fg(theme.muted).centered());
+        }
+        let inner_area = outer_block.inner(area);
+        outer_block.render(area, buf);
+
+        let layout = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3), // Summary
+                Constraint::Min(5),    // Chart and subjects
+            ])
+            .margin(1)
+            .split(inner_area);
+        let summary_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+                Constraint::Ratio(1, 4),
+            ])
+            .split(layout[0]);
+        let main_layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(70), Constraint::Percentage(30)])
+            .split(layout[1]);
+
+        let summary = [
+            (" Today ", format!("{} min", stats.focus_on(today) / 60), theme.work),
+            (" Current streak ", format!("{} days", current_streak), theme.short_break),
+            (" Longest streak ", format!("{} days", longest_streak), theme.alert),
+            (" Interruptions ", format!("{} internal / {} external", internal, external), theme.long_break),
+        ];
+        for (i, (title, value, color)) in summary.into_iter().enumerate() {
+            Paragraph::new(value)
+                .alignment(Alignment::Center)
+                .style(Style::default().fg(theme.text).add_modifier(Modifier::BOLD))
+                .block(
+                    Block::default()
+                        .title(title)
+                        .title_alignment(Alignment::Center)
+                        .borders(Borders::ALL)
+                        .border_type(BorderType::Rounded)
+                        .border_style(Style::default().fg(color)),
+                )
+                .render(summary_layout[i], buf);
+        }
+
+        // Bar chart of focused minutes per day
+        let chart_block = Block::default()
+            .title(format!(
+                " Focused minutes: {} - {} ",
+                from.format("%b %d"),
+                to.format("%b %d")
+            ))
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.work));
+        let days = stats_tab.range.days() as u16;
+        let bar_width = (chart_block.inner(main_layout[0]).width / days)
+            .saturating_sub(1)
+            .max(1);
+        let bars: Vec<Bar> = stats
+            .focus_per_day(from, to)
+            .into_iter()
+            .map(|(day, seconds)| {
+                let label = match stats_tab.range {
+                    StatsRange::Week => day.format("%a").to_string(),
+                    StatsRange::Month => day.format("%d").to_string(),
+                };
+                Bar::default()
+                    .value((seconds / 60) as u64)
+                    .label(Line::from(label))
+                    .style(Style::default().fg(if day == today { theme.accent } else { theme.work }))
+                    .value_style(Style::default().fg(theme.background).bg(if day == today { theme.accent } else { theme.work }))
+            })
+            .collect();
+        BarChart::default()
+            .block(chart_block)
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .render(main_layout[0], buf);
+
+        // Subject breakdown for the same window
+        let subject_lines: Vec<Line> = match stats.focus_by_subject(from, to) {
+            subjects if subjects.is_empty() => vec![Line::from("No sessions yet").fg(theme.muted)],
+            subjects => subjects
+                .into_iter()
+                .map(|(subject, seconds)| {
+                    Line::from(vec![
+                        Span::styled(subject, Style::default().fg(theme.text)),
+                        Span::styled(format!(" {} min", seconds / 60), Style::default().fg(theme.short_break)),
+                    ])
+                })
+                .collect(),
+        };
+        Paragraph::new(subject_lines)
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::LightGreen));
-        text.render(area, buf);
+            .block(
+                Block::default()
+                    .title(" Subjects ")
+                    .title_alignment(Alignment::Center)
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Rounded)
+                    .border_style(Style::default().fg(theme.short_break)),
+            )
+            .render(main_layout[1], buf);
+
+        // Apply background color to the entire area
+        fill_background(area, buf, theme.background);
     }
 
     pub fn draw(&self, frame: &mut Frame) {
@@ -255,78 +551,197 @@ impl App {
 //        gauge.render(gauge_layout[1], buf);
 //    }
 //}
+impl Pomodoro {
+    /// Seconds shown on the clock, zero while the countdown is hidden.
+    fn shown_time(&self) -> i64 {
+        let hidden = self.get_setting_ref().borrow().ui_settings.hide_work_countdown && self.timer.get_running();
+        if hidden {0} else {self.timer.get_timeleft().max(0)}
+    }
+    /// State, `MM:SS` clock and iteration on one line.
+    pub fn status_line(&self, theme: &Theme) -> Line<'static> {
+        let time = self.shown_time();
+        let mut spans = vec![
+            Span::styled(self.timer.get_state_label(), Style::default().fg(self.state_colour(theme)).add_modifier(Modifier::BOLD)),
+            Span::raw(" "),
+            Span::styled(format!("{:02}:{:02}", time / 60, time % 60), Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)),
+            Span::raw(" "),
+            Span::styled(
+                format!("{}/{}", self.timer.get_iteration(), self.timer.get_total_iterations()),
+                Style::default().fg(theme.work),
+            ),
+        ];
+        let interruptions = self.timer.get_interruptions().len();
+        if interruptions > 0 {
+            spans.push(Span::styled(format!(" !{interruptions}"), Style::default().fg(theme.alert)));
+        }
+        if !self.timer.get_running() {
+            spans.push(Span::styled(" paused", Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC)));
+        }
+        Line::from(spans)
+    }
+    /// The profile's colour for the current phase, or the theme's one for its state.
+    fn state_colour(&self, theme: &Theme) -> Color {
+        match self.timer.get_phase().and_then(Phase::get_colour) {
+            Some(colour) => ColorSupport::current().adapt(colour),
+            None => theme.state(&self.timer.get_current_state()),
+        }
+    }
+    /// The timer's border with the preset in use and the last failed hook.
+    fn timer_block(&self, theme: &Theme) -> Block<'static> {
+        let mut block = Block::default()
+            .title(" Pomodoro Timer ")
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .style(Style::default().fg(theme.accent));
+        if let Some(preset) = &self.get_setting_ref().borrow().preset {
+            block = block.title(Line::from(format!(" {preset} ")).right_aligned());
+        }
+        match self.get_hook_failure() {
+            Some(failure) => block.title_bottom(Line::from(format!(" {failure} ")).fg(theme.alert).centered()),
+            None => block,
+        }
+    }
+    /// Interruptions of the current interval by kind, `None` if there were none.
+    fn interruptions_text(&self) -> Option<String> {
+        let interruptions = self.timer.get_interruptions();
+        let internal = interruptions.iter().filter(|i| i.kind == InterruptionKind::Internal).count();
+        let external = interruptions.len() - internal;
+        (!interruptions.is_empty()).then(|| format!("interrupted {internal} internal / {external} external"))
+    }
+    /// The active task and how far along it is, or how many tasks are waiting.
+    fn task_text(&self) -> Option<String> {
+        let tasks = self.get_tasks_ref();
+        let tasks = tasks.borrow();
+        match tasks.get_active() {
+            Some(task) => Some(match tasks.remaining().saturating_sub(1) {
+                0 => format!("Task: {} ({}/{})", task.name, task.pomodoros, task.estimate),
+                more => format!("Task: {} ({}/{}), {} more to do", task.name, task.pomodoros, task.estimate, more),
+            }),
+            None if tasks.remaining() > 0 => Some(format!("No active task, {} to do", tasks.remaining())),
+            None => None,
+        }
+    }
+    /// Status line, subject, task and a one-line gauge, as many as fit.
+    fn render_compact(&self, area: Rect, buf: &mut Buffer, theme: &Theme) {
+        let inner = match area.height >= 5 {
+            true => {
+                let block = self.timer_block(theme);
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            false => area,
+        };
+        let subject_text = match self.timer.get_subject() {
+            Some(subject) => format!("Subject: {}", subject),
+            None => "No subject".to_string(),
+        };
+        let state_colour = self.state_colour(theme);
+        let progress = self.timer.get_total_elapsed_time() as f64 / self.timer.get_total_time().max(1) as f64;
+        let mut lines = vec![
+            self.status_line(theme),
+            Line::styled(subject_text, Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC)),
+        ];
+        if let Some(task) = self.task_text() {
+            lines.push(Line::styled(task, Style::default().fg(theme.work)));
+        }
+        let text_rows = lines.len();
+        let rows = (inner.height as usize).min(text_rows + 1);
+        let layout = Layout::vertical(vec![Constraint::Length(1); rows]).flex(Flex::Center).split(inner);
+
+        for (line, row) in lines.into_iter().zip(layout.iter()) {
+            Paragraph::new(line).alignment(Alignment::Center).render(*row, buf);
+        }
+        if let Some(row) = layout.get(text_rows) {
+            let gauge_area = Layout::horizontal([Constraint::Percentage(70)]).flex(Flex::Center).split(*row)[0];
+            LineGauge::default()
+                .filled_style(Style::default().fg(state_colour))
+                .unfilled_style(Style::default().fg(theme.muted))
+                .label(Span::styled(format!("{:>3}%", (progress * 100.0) as u16), Style::default().fg(theme.text)))
+                .ratio(progress.clamp(0.0, 1.0))
+                .render(gauge_area, buf);
+        }
+        fill_background(area, buf, theme.background);
+    }
+}
 impl Widget for &Pomodoro {
     fn render(self, area: ratatui::prelude::Rect, buf: &mut ratatui::prelude::Buffer) {
+        let theme = self.get_setting_ref().borrow().get_theme();
+        if area.width < FULL_TIMER_WIDTH || area.height < FULL_TIMER_HEIGHT {
+            self.render_compact(area, buf, &theme);
+            return;
+        }
         let time = self.timer.get_timeleft();
         let total_time = self.timer.get_total_time();
         let elapsed_time = self.timer.get_total_elapsed_time();
-        let now_text = format!("Now: {}", self.timer.get_current_state());
+        let now_text = format!("Now: {}", self.timer.get_state_label());
         let progress = (elapsed_time) as f64 / total_time as f64;
-        let iterations_text = format!(
-            "{}/{} iterations",
+        let mut iterations_text = format!(
+            "{}/{} {}",
             self.timer.get_iteration(),
-            self.timer.get_total_iterations()
+            self.timer.get_total_iterations(),
+            if self.timer.get_phases().is_empty() {"iterations"} else {"phases"}
         );
+        if let Some(interruptions) = self.interruptions_text() {
+            iterations_text = format!("{iterations_text}, {interruptions}");
+        }
 
-        let outer_block = Block::default()
-            .title(" Pomodoro Timer ")
-            .borders(Borders::ALL)
-            .border_type(BorderType::Rounded)
-            .style(Style::default().fg(YELLOW)); // Gruvbox yellow for border
+        let outer_block = self.timer_block(&theme);
 
         // Determine timer display based on settings and state
-        let (timer_style, text_of_timer) = match self
+        let (timer_style, clock) = match self
             .get_setting_ref()
             .borrow()
             .ui_settings
             .hide_work_countdown
         {
             true if self.timer.get_running() => {
-                (Style::default().fg(RED), format_ascii_time("00:00:00"))
+                (Style::default().fg(theme.alert), "00:00:00".to_string())
             }
             true => (
-                Style::default().fg(RED),
-                format_ascii_time(&format!(
+                Style::default().fg(theme.alert),
+                format!(
                     "{:02}:{:02}:{:02}",
                     time / 3600,
                     (time % 3600) / 60,
                     time % 60
-                )),
+                ),
             ),
             false => (
-                Style::default().fg(YELLOW).add_modifier(Modifier::BOLD),
-                format_ascii_time(&format!(
+                Style::default().fg(theme.accent).add_modifier(Modifier::BOLD),
+                format!(
                     "{:02}:{:02}:{:02}",
                     time / 3600,
                     (time % 3600) / 60,
                     time % 60
-                )),
+                ),
             ),
         };
 
-        let timer_text = Paragraph::new(text_of_timer)
-            .alignment(Alignment::Center)
-            .style(timer_style);
-
         // Style the state indicator based on current state
-        let now_paragraph_style = match self.timer.get_current_state() {
-            crate::timer::PomodoroState::Work(_) => Style::default().fg(BLUE),
-            crate::timer::PomodoroState::Break(_) => Style::default().fg(GREEN),
-        };
+        let now_paragraph_style = Style::default().fg(self.state_colour(&theme));
 
         let now_paragraph = Paragraph::new(now_text)
             .alignment(Alignment::Center)
             .style(now_paragraph_style);
 
+        let subject_text = match self.timer.get_subject() {
+            Some(subject) => format!("Subject: {}", subject),
+            None => "No subject".to_string(),
+        };
+        let subject_paragraph = Paragraph::new(subject_text)
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.muted).add_modifier(Modifier::ITALIC));
+        let task_paragraph = Paragraph::new(self.task_text().unwrap_or_default())
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.work));
+
         let count_paragraph = Paragraph::new(iterations_text)
             .alignment(Alignment::Center)
-            .style(Style::default().fg(BLUE).add_modifier(Modifier::ITALIC));
+            .style(Style::default().fg(theme.work).add_modifier(Modifier::ITALIC));
 
         // Create gauge with proper title
-        let gauge_style = match self.timer.get_current_state() {
-            crate::timer::PomodoroState::Work(_) => BLUE,
-            crate::timer::PomodoroState::Break(_) => GREEN,
-        };
+        let gauge_style = self.state_colour(&theme);
 
         let gauge = Gauge::default()
             .block(
@@ -352,7 +767,9 @@ impl Widget for &Pomodoro {
             .constraints([
                 Constraint::Percentage(top_margin_percent), // Top margin - dynamic
                 Constraint::Length(1),                      // Now text
-                Constraint::Length(5),                      // Small gap
+                Constraint::Length(1),                      // Subject
+                Constraint::Length(1),                      // Active task
+                Constraint::Length(3),                      // Small gap
                 Constraint::Min(5),    // ASCII timer - give it space to breathe
                 Constraint::Length(1), // Small gap
                 Constraint::Length(1), // count
@@ -370,37 +787,48 @@ impl Widget for &Pomodoro {
                 Constraint::Percentage(60),
                 Constraint::Percentage(20),
             ])
-            .split(layout[7]);
+            .split(layout[9]);
+
+        // Pick the font for the space the clock got and center it vertically
+        let clock_area = layout[5];
+        let font = Font::select(&self.get_setting_ref().borrow().ui_settings, &clock, clock_area.width, clock_area.height);
+        let font_height = (font.height() as u16).min(clock_area.height);
+        let clock_area = Rect {
+            y: clock_area.y + (clock_area.height - font_height) / 2,
+            height: font_height,
+            ..clock_area
+        };
+        let timer_text = Paragraph::new(font.render(&clock))
+            .alignment(Alignment::Center)
+            .style(timer_style);
 
         // Render all elements
         outer_block.render(area, buf);
         now_paragraph.render(layout[1], buf);
-        timer_text.render(layout[3], buf);
-        count_paragraph.render(layout[5], buf);
+        subject_paragraph.render(layout[2], buf);
+        task_paragraph.render(layout[3], buf);
+        timer_text.render(clock_area, buf);
+        count_paragraph.render(layout[7], buf);
         gauge.render(gauge_layout[1], buf);
 
         // Set background color while preserving existing styles
-        for y in area.top()..area.bottom() {
-            for x in area.left()..area.right() {
-                let cell = buf.cell_mut((x, y)).expect("should work lmao");
-                if cell.style().bg == Some(Color::Reset) {
-                    cell.set_style(cell.style().bg(BG));
-                }
-            }
-        }
+        fill_background(area, buf, theme.background);
     }
 }
 impl Widget for &SettingsTab {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        // Create a more compact vertical layout using percentages
+        let theme = self.get_theme();
+        // The settings panels get the rows they need, subjects take what's left
         let outer_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Percentage(5),  // Top gap
-                Constraint::Percentage(35), // Pomodoro Settings
-                Constraint::Percentage(5),  // Middle gap
-                Constraint::Percentage(35), // Other Settings
-                Constraint::Percentage(20), // Bottom gap -
+                Constraint::Percentage(2),  // Top gap
+                Constraint::Length(16),     // Pomodoro Settings
+                Constraint::Length(1),      // Middle gap
+                Constraint::Length(8),      // Other Settings
+                Constraint::Length(1),      // Middle gap
+                Constraint::Min(3),         // Subjects
+                Constraint::Percentage(4),  // Bottom gap
             ])
             .split(area);
 
@@ -415,9 +843,11 @@ impl Widget for &SettingsTab {
 
         let pomodoro_layout = horizontal_layout.split(outer_layout[1]);
         let other_layout = horizontal_layout.split(outer_layout[3]);
+        let subjects_layout = horizontal_layout.split(outer_layout[5]);
 
         let pomodoro_settings_area = pomodoro_layout[1];
         let other_settings_area = other_layout[1];
+        let subjects_area = subjects_layout[1];
 
         // Create panel blocks with improved styling
         let pomodoro_box = Block::default()
@@ -425,14 +855,14 @@ impl Widget for &SettingsTab {
             .title_alignment(Alignment::Center)
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .border_style(Style::default().fg(BLUE));
+            .border_style(Style::default().fg(theme.work));
 
         let other_settings_box = Block::default()
             .title(" Other Settings ")
             .title_alignment(Alignment::Center)
             .borders(Borders::ALL)
             .border_type(BorderType::Rounded)
-            .border_style(Style::default().fg(RED));
+            .border_style(Style::default().fg(theme.alert));
 
         let pomodoro_inner_area = pomodoro_box.inner(pomodoro_settings_area);
         let other_inner_area = other_settings_box.inner(other_settings_area);
@@ -441,14 +871,22 @@ impl Widget for &SettingsTab {
         let pomodoro_inner_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Percentage(5),  // Top margin
-                Constraint::Percentage(15), // Work Time label
-                Constraint::Percentage(15), // Work Time value
-                Constraint::Percentage(15), // Break Time label
-                Constraint::Percentage(15), // Break Time value
-                Constraint::Percentage(15), // Iterations label
-                Constraint::Percentage(15), // Iterations value
-                Constraint::Percentage(5),  // Bottom margin
+                Constraint::Min(0),    // Top margin
+                Constraint::Length(1), // Work Time label
+                Constraint::Length(1), // Work Time value
+                Constraint::Length(1), // Break Time label
+                Constraint::Length(1), // Break Time value
+                Constraint::Length(1), // Iterations label
+                Constraint::Length(1), // Iterations value
+                Constraint::Length(1), // Long Break Time label
+                Constraint::Length(1), // Long Break Time value
+                Constraint::Length(1), // Long Break Interval label
+                Constraint::Length(1), // Long Break Interval value
+                Constraint::Length(1), // Profile label
+                Constraint::Length(1), // Profile value
+                Constraint::Length(1), // Preset label
+                Constraint::Length(1), // Preset value
+                Constraint::Min(0),    // Bottom margin
             ])
             .split(pomodoro_inner_area);
 
@@ -456,12 +894,14 @@ impl Widget for &SettingsTab {
         let other_inner_layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Percentage(5),  // Top margin
-                Constraint::Percentage(15), // Pause setting label
-                Constraint::Percentage(15), // Pause setting value
-                Constraint::Percentage(15), // Hide clock label
-                Constraint::Percentage(15), // Hide clock value
-                Constraint::Percentage(5),  // Bottom margin
+                Constraint::Min(0),    // Top margin
+                Constraint::Length(1), // Pause setting label
+                Constraint::Length(1), // Pause setting value
+                Constraint::Length(1), // Hide clock label
+                Constraint::Length(1), // Hide clock value
+                Constraint::Length(1), // Theme label
+                Constraint::Length(1), // Theme value
+                Constraint::Min(0),    // Bottom margin
             ])
             .split(other_inner_area);
 
@@ -472,7 +912,7 @@ impl Widget for &SettingsTab {
         // Create and render work time settings
         let work_time_text = Paragraph::new("Work Time")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White))
+            .style(Style::default().fg(theme.text))
             .add_modifier(Modifier::BOLD);
 
         let work_time_value =
@@ -483,7 +923,7 @@ impl Widget for &SettingsTab {
         // Create and render break time settings
         let break_time_text = Paragraph::new("Break Time")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White))
+            .style(Style::default().fg(theme.text))
             .add_modifier(Modifier::BOLD);
 
         let break_time_value =
@@ -494,13 +934,61 @@ impl Widget for &SettingsTab {
         // Create and render iterations settings
         let iterations_text = Paragraph::new("Iterations")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White))
+            .style(Style::default().fg(theme.text))
             .add_modifier(Modifier::BOLD);
 
         let iterations_value = Paragraph::new(format!("{} iters", self.timer_settings.iterations))
             .alignment(Alignment::Center)
             .style(self.highlight_selected(2));
 
+        // Create and render long break settings
+        let long_break_time_text = Paragraph::new("Long Break Time")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.text))
+            .add_modifier(Modifier::BOLD);
+
+        let long_break_time_value =
+            Paragraph::new(format!("{} min", self.timer_settings.long_break_time / 60))
+                .alignment(Alignment::Center)
+                .style(self.highlight_selected(3));
+
+        let long_break_interval_text = Paragraph::new("Long Break Every")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.text))
+            .add_modifier(Modifier::BOLD);
+
+        let long_break_interval_val = match self.timer_settings.long_break_interval {
+            0 => "off".to_string(),
+            interval => format!("{} iters", interval),
+        };
+        let long_break_interval_value = Paragraph::new(long_break_interval_val)
+            .alignment(Alignment::Center)
+            .style(self.highlight_selected(4));
+
+        let profile_text = Paragraph::new("Profile")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.text))
+            .add_modifier(Modifier::BOLD);
+
+        let profile_value = Paragraph::new(self.timer_settings.profile.as_deref().unwrap_or("classic"))
+            .alignment(Alignment::Center)
+            .style(self.highlight_selected(5));
+
+        let preset_text = Paragraph::new("Preset")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.text))
+            .add_modifier(Modifier::BOLD);
+
+        let preset_val = match (&self.preset, self.preset_edited()) {
+            (Some(name), true) => format!("{name} (edited)"),
+            (Some(name), false) => name.clone(),
+            (None, _) if self.presets.is_empty() => "none configured".to_string(),
+            (None, _) => "none".to_string(),
+        };
+        let preset_value = Paragraph::new(preset_val)
+            .alignment(Alignment::Center)
+            .style(self.highlight_selected(6));
+
         // Render the pomodoro settings
         work_time_text.render(pomodoro_inner_layout[1], buf);
         work_time_value.render(pomodoro_inner_layout[2], buf);
@@ -508,11 +996,19 @@ impl Widget for &SettingsTab {
         break_time_value.render(pomodoro_inner_layout[4], buf);
         iterations_text.render(pomodoro_inner_layout[5], buf);
         iterations_value.render(pomodoro_inner_layout[6], buf);
+        long_break_time_text.render(pomodoro_inner_layout[7], buf);
+        long_break_time_value.render(pomodoro_inner_layout[8], buf);
+        long_break_interval_text.render(pomodoro_inner_layout[9], buf);
+        long_break_interval_value.render(pomodoro_inner_layout[10], buf);
+        profile_text.render(pomodoro_inner_layout[11], buf);
+        profile_value.render(pomodoro_inner_layout[12], buf);
+        preset_text.render(pomodoro_inner_layout[13], buf);
+        preset_value.render(pomodoro_inner_layout[14], buf);
 
         // Create and render pause setting
         let pause_change_state_text = Paragraph::new("Pause before new iteration")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White))
+            .style(Style::default().fg(theme.text))
             .add_modifier(Modifier::BOLD);
 
         let pause_change_state_val = if self.ui_settings.pause_after_state_change {
@@ -523,12 +1019,12 @@ impl Widget for &SettingsTab {
 
         let pause_change_state_value = Paragraph::new(pause_change_state_val.to_string())
             .alignment(Alignment::Center)
-            .style(self.highlight_selected(3));
+            .style(self.highlight_selected(7));
 
         // Create and render hide clock setting
         let hide_clock_text = Paragraph::new("Hide clock on work time")
             .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::White))
+            .style(Style::default().fg(theme.text))
             .add_modifier(Modifier::BOLD);
 
         let hide_clock_val = if self.ui_settings.hide_work_countdown {
@@ -539,23 +1035,61 @@ impl Widget for &SettingsTab {
 
         let hide_clock_value = Paragraph::new(hide_clock_val)
             .alignment(Alignment::Center)
-            .style(self.highlight_selected(4));
+            .style(self.highlight_selected(8));
+
+        // Create and render theme setting
+        let theme_text = Paragraph::new("Theme")
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(theme.text))
+            .add_modifier(Modifier::BOLD);
+
+        let theme_value = Paragraph::new(self.ui_settings.theme.as_str())
+            .alignment(Alignment::Center)
+            .style(self.highlight_selected(9));
 
         // Render the other settings
         pause_change_state_text.render(other_inner_layout[1], buf);
         pause_change_state_value.render(other_inner_layout[2], buf);
         hide_clock_text.render(other_inner_layout[3], buf);
         hide_clock_value.render(other_inner_layout[4], buf);
+        theme_text.render(other_inner_layout[5], buf);
+        theme_value.render(other_inner_layout[6], buf);
 
-        // Apply background color to the entire area
-        for y in area.top()..area.bottom() {
-            for x in area.left()..area.right() {
-                let cell = buf.cell_mut((x, y)).expect("Should work");
-                if cell.style().bg == Some(Color::Reset) {
-                    cell.set_style(cell.style().bg(BG));
-                }
+        // Create and render the subject list, scrolled so the selected one stays visible
+        let subjects_box = Block::default()
+            .title(" Subjects ")
+            .title_alignment(Alignment::Center)
+            .borders(Borders::ALL)
+            .border_type(BorderType::Rounded)
+            .border_style(Style::default().fg(theme.short_break));
+        let subjects_inner_area = subjects_box.inner(subjects_area);
+        subjects_box.render(subjects_area, buf);
+
+        let subjects = &self.stats_setting.subjects;
+        let visible = subjects_inner_area.height.max(1) as usize;
+        let first = self
+            .get_selected_subject()
+            .map_or(0, |selected| selected.saturating_sub(visible - 1));
+        let subject_lines: Vec<Line> = match subjects.is_empty() {
+            true => {
+                let add_key = Keymap::try_from(&self.keymap).unwrap_or_default().hint(&[Action::AddSubject]).unwrap_or_default();
+                vec![Line::from(format!("No subjects, press {add_key} to add one")).fg(theme.muted)]
             }
-        }
+            false => subjects
+                .iter()
+                .enumerate()
+                .skip(first)
+                .map(|(i, subject)| {
+                    Line::from(subject.as_str()).style(self.highlight_selected(SettingsTab::get_subject_row(i)))
+                })
+                .collect(),
+        };
+        Paragraph::new(subject_lines)
+            .alignment(Alignment::Center)
+            .render(subjects_inner_area, buf);
+
+        // Apply background color to the entire area
+        fill_background(area, buf, theme.background);
     }
 }
 //impl Widget for &SettingsTab {
@@ -709,47 +1243,80 @@ impl Widget for &SettingsTab {
 
 impl SettingsTab {
     fn highlight_selected(&self, setting_num: usize) -> Style {
+        let theme = self.get_theme();
         if setting_num == self.selected_setting {
-            Style::default().fg(YELLOW).add_modifier(Modifier::BOLD)
+            Style::default().fg(theme.accent).add_modifier(Modifier::BOLD)
         } else {
-            Style::default().fg(GREEN)
+            Style::default().fg(theme.short_break)
         }
     }
 }
-fn format_ascii_time(input: &str) -> String {
-    let mut output = vec![String::new(); 7];
-
-    for ch in input.chars() {
-        let index = match ch {
-            '0'..='9' => ch as usize - '0' as usize,
-            ':' => 10,
-            _ => continue,
-        };
-
-        let ascii_lines: Vec<&str> = ASCII_NUMBERS[index].lines().collect();
-
-        for (i, line) in ascii_lines.iter().enumerate() {
-            output[i].push_str(line);
-            output[i].push_str("  ");
+/// Paints `colour` under every cell that doesn't have a background yet.
+fn fill_background(area: Rect, buf: &mut Buffer, colour: Color) {
+    for y in area.top()..area.bottom() {
+        for x in area.left()..area.right() {
+            if let Some(cell) = buf.cell_mut((x, y)) {
+                if cell.style().bg == Some(Color::Reset) {
+                    cell.set_style(cell.style().bg(colour));
+                }
+            }
         }
     }
-
-    output.join("\n")
 }
-
-const ASCII_NUMBERS: [&str; 11] = [
-    "  ███  \n █   █ \n█     █\n█     █\n█     █\n █   █ \n  ███  ", // 0
-    "   █   \n  ██   \n █ █   \n   █   \n   █   \n   █   \n ████  ", // 1
-    " ███   \n█   █  \n    █  \n   █   \n  █    \n █     \n█████  ", // 2
-    " ███   \n█   █  \n    █  \n  ██   \n    █  \n█   █  \n ███   ", // 3
-    "   ██  \n  █ █  \n █  █  \n█   █  \n█████  \n    █  \n    █  ", // 4
-    "█████  \n█      \n████   \n    █  \n    █  \n█   █  \n ███   ", // 5
-    "  ███  \n █     \n█      \n█ ███  \n█    █ \n █   █ \n  ███  ", // 6
-    "█████  \n    █  \n   █   \n  █    \n █     \n █     \n █     ", // 7
-    "  ███  \n █   █ \n █   █ \n  ███  \n █   █ \n █   █ \n  ███  ", // 8
-    "  ███  \n █   █ \n █   █ \n  ████ \n     █ \n    ██ \n  ███  ", // 9
-    "        \n   █    \n   █    \n        \n   █    \n   █    \n        ", // :
-];
+fn footer_text(keymap: &Keymap, items: FooterItems) -> String {
+    items
+        .iter()
+        .filter_map(|(actions, label)| Some(format!("{}: {}", keymap.hint(actions)?, label)))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+fn render_confirm_popup(title: &str, question: &str, keymap: &Keymap, theme: &Theme, area: Rect, buf: &mut Buffer) {
+    let popup_area = centered_rect(40, 30, area);
+    let popup_block = Block::default()
+        .borders(Borders::NONE)
+        .style(Style::default().bg(theme.background));
+    let popup_layout = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(30)])
+        .vertical_margin(0)
+        .split(popup_area);
+    let popup_yes_no_layout = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .horizontal_margin(0)
+        .split(popup_layout[1]);
+    let yes_paragraph = Paragraph::new(format!("<{}> Yes", keymap.hint(&[Action::Yes]).unwrap_or_default()))
+        .alignment(Alignment::Center)
+        .centered()
+        .style(Style::default().fg(theme.short_break))
+        .block(
+            Block::default()
+                .borders(Borders::all())
+                .border_type(BorderType::Rounded),
+        );
+    let no_paragraph = Paragraph::new(format!("<{}> No", keymap.hint(&[Action::No]).unwrap_or_default()))
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(theme.alert))
+        .block(
+            Block::default()
+                .borders(Borders::all())
+                .border_type(BorderType::Rounded),
+        );
+    let question_paragraph = Paragraph::new(question)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(theme.muted))
+        .block(
+            Block::default()
+                .borders(Borders::all())
+                .border_type(BorderType::Rounded)
+                .title(title),
+        );
+    Clear.render(area, buf);
+    popup_block.render(popup_area, buf);
+    question_paragraph.render(popup_layout[0], buf);
+    yes_paragraph.render(popup_yes_no_layout[0], buf);
+    no_paragraph.render(popup_yes_no_layout[1], buf);
+}
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     // Cut the given rectangle into three vertical pieces
     let popup_layout = Layout::default()
@@ -774,11 +1341,57 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 
 #[cfg(test)]
 mod test {
-    use crate::ui::format_ascii_time;
+    use std::{cell::RefCell, rc::Rc};
+
+    use ratatui::{backend::TestBackend, buffer::Buffer, layout::Rect, widgets::Widget, Terminal};
+
+    use super::LayoutMode;
+    use crate::{app::App, font::Font, romodoro::Pomodoro, settings::SettingsTab, stats::SessionStore};
 
     #[test]
     fn ascii_text_works() {
         let time = "01:32:29";
-        println!("{}", format_ascii_time(time));
+        println!("{}", Font::default().render(time));
+    }
+    #[test]
+    fn layout_mode_by_size() {
+        assert_eq!(LayoutMode::for_area(Rect::new(0, 0, 120, 40)), LayoutMode::Full);
+        assert_eq!(LayoutMode::for_area(Rect::new(0, 0, 40, 12)), LayoutMode::Compact);
+        assert_eq!(LayoutMode::for_area(Rect::new(0, 0, 120, 20)), LayoutMode::Compact);
+        assert_eq!(LayoutMode::for_area(Rect::new(0, 0, 20, 3)), LayoutMode::StatusLine);
+    }
+    #[test]
+    fn subject_hint_follows_keymap() {
+        let mut settings = SettingsTab::default();
+        settings.keymap.0.insert("add_subject".to_string(), vec!["N".to_string()]);
+        let area = Rect::new(0, 0, 120, 40);
+        let mut buf = Buffer::empty(area);
+        (&settings).render(area, &mut buf);
+        let text: String = buf.content().iter().map(|cell| cell.symbol()).collect();
+        assert!(text.contains("No subjects, press N to add one"));
+    }
+    #[tokio::test]
+    async fn small_panes_keep_the_clock() {
+        let path = std::env::temp_dir().join(format!("romodoro-ui-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let settings = Rc::new(RefCell::new(SettingsTab::default()));
+        let stats = Rc::new(RefCell::new(SessionStore::from_path(&path).unwrap()));
+        let (tx, _rx) = tokio::sync::mpsc::channel(4);
+        let (tx_commands, rx_commands) = tokio::sync::mpsc::channel(4);
+        let pomodoro = Pomodoro::new(tx, rx_commands, tx_commands, settings.clone(), stats);
+        let app = App::new(pomodoro, settings);
+
+        let screen = |width, height| {
+            let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+            terminal.draw(|frame| app.draw(frame)).unwrap();
+            terminal.backend().to_string()
+        };
+        let compact = screen(40, 12);
+        assert!(compact.contains("Work 30:00 1/4 paused"));
+        assert!(compact.contains("No subject"));
+        assert!(!compact.contains("╭ Menu"));
+        let status = screen(20, 3);
+        assert!(status.contains("Work 30:00 1/4"));
+        assert_eq!(status.lines().filter(|line| line.trim_matches(['"', ' ']).is_empty()).count(), 2);
     }
 }